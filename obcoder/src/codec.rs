@@ -0,0 +1,412 @@
+use crate::error::check;
+use crate::{
+    av_codec_is_decoder, av_codec_is_encoder, av_codec_iterate, av_pix_fmt_desc_get,
+    avcodec_alloc_context3, avcodec_find_encoder_by_name, avcodec_free_context, avcodec_open2,
+    get_decoder, AVCodec, AVCodecID, AVCodecID_AV_CODEC_ID_AV1, AVCodecID_AV_CODEC_ID_H264,
+    AVCodecID_AV_CODEC_ID_HEVC, AVCodecID_AV_CODEC_ID_VP9, AVMediaType_AVMEDIA_TYPE_VIDEO,
+    AVPixelFormat, AVPixelFormat_AV_PIX_FMT_NONE, AVPixelFormat_AV_PIX_FMT_YUV420P, AVRational,
+    AV_CODEC_CAP_HARDWARE, AV_CODEC_CAP_HYBRID, AV_PIX_FMT_FLAG_HWACCEL,
+};
+use std::error::Error;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_void};
+use std::ptr;
+
+/// `AV_PROFILE_UNKNOWN`，`AVCodec::profiles` 的结束标记
+const PROFILE_UNKNOWN: i32 = -99;
+/// 验证时打开编码器使用的尺寸
+const PROBE_SIZE: (i32, i32) = (640, 360);
+/// 探测最大分辨率时依次尝试的尺寸，从大到小
+const RESOLUTION_LADDER: [(i32, i32); 6] = [
+    (8192, 4320),
+    (7680, 4320),
+    (4096, 2160),
+    (3840, 2160),
+    (2560, 1440),
+    (1920, 1080),
+];
+
+/// 编码器或解码器
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodecKind {
+    Encoder,
+    Decoder,
+}
+
+/// 支持的视频编码格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum VideoCodec {
+    #[default]
+    H264,
+    Hevc,
+    Vp9,
+    Av1,
+}
+
+/// 编码器输出的码流封装方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitstreamFormat {
+    /// 以起始码分隔的 NAL 单元 (H.264/HEVC)
+    AnnexB,
+    /// 每个数据包是一个完整的帧或超帧 (VP9)
+    Frame,
+    /// 低开销格式的 OBU 序列 (AV1)
+    Obu,
+}
+
+impl VideoCodec {
+    pub const ALL: [VideoCodec; 4] = [
+        VideoCodec::H264,
+        VideoCodec::Hevc,
+        VideoCodec::Vp9,
+        VideoCodec::Av1,
+    ];
+
+    pub fn id(self) -> AVCodecID {
+        match self {
+            VideoCodec::H264 => AVCodecID_AV_CODEC_ID_H264,
+            VideoCodec::Hevc => AVCodecID_AV_CODEC_ID_HEVC,
+            VideoCodec::Vp9 => AVCodecID_AV_CODEC_ID_VP9,
+            VideoCodec::Av1 => AVCodecID_AV_CODEC_ID_AV1,
+        }
+    }
+
+    pub fn from_id(id: AVCodecID) -> Option<Self> {
+        Self::ALL.into_iter().find(|codec| codec.id() == id)
+    }
+
+    /// RTP/SDP 中使用的 MIME 类型
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            VideoCodec::H264 => "video/H264",
+            VideoCodec::Hevc => "video/H265",
+            VideoCodec::Vp9 => "video/VP9",
+            VideoCodec::Av1 => "video/AV1",
+        }
+    }
+
+    pub fn bitstream_format(self) -> BitstreamFormat {
+        match self {
+            VideoCodec::H264 | VideoCodec::Hevc => BitstreamFormat::AnnexB,
+            VideoCodec::Vp9 => BitstreamFormat::Frame,
+            VideoCodec::Av1 => BitstreamFormat::Obu,
+        }
+    }
+}
+
+/// 编码档次
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Profile {
+    pub id: i32,
+    pub name: String,
+}
+
+/// 经过实际打开验证的编解码器
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodecInfo {
+    pub name: String,
+    pub long_name: String,
+    pub kind: CodecKind,
+    pub codec_id: AVCodecID,
+    pub hardware: bool,
+    /// 声明支持的像素格式，空表示未声明
+    pub pixel_formats: Vec<AVPixelFormat>,
+    pub profiles: Vec<Profile>,
+    /// 能打开的最大分辨率
+    ///
+    /// [`probe_encoders`] 不会填写该字段（逐个探测要多次初始化硬件会话，开销太大），
+    /// 需要时对选中的编码器调用 [`probe_max_resolution`]；仍为 `None` 表示 1080p 也打不开。
+    pub max_resolution: Option<(i32, i32)>,
+}
+
+/// 被跳过的候选编解码器及原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rejection {
+    pub codec: String,
+    pub reason: String,
+}
+
+impl std::fmt::Display for Rejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.codec, self.reason)
+    }
+}
+
+/// 所有候选都不可用
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelectionError {
+    pub rejected: Vec<Rejection>,
+}
+
+impl std::fmt::Display for SelectionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "no usable codec")?;
+        for (i, rejection) in self.rejected.iter().enumerate() {
+            write!(f, "{} {}", if i == 0 { ":" } else { ";" }, rejection)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for SelectionError {}
+
+/// 当前平台的硬件编码器；H.264 的顺序与 `ob_codec.h` 中的 `encoders[]` 一致
+///
+/// QSV 编码器只接受 NV12/P010 输入，使用默认的 YUV420P 时会被跳过，需要把
+/// [`EncoderBuilder::pixel_format`](crate::EncoderBuilder::pixel_format) 和
+/// `ObStream` 的目标格式都设为 NV12。VAAPI 编码器只接受 `AV_PIX_FMT_VAAPI` 硬件帧，
+/// 需要设备上下文和帧上传，目前不在候选中。
+pub fn hardware_encoders(codec: VideoCodec) -> Vec<&'static str> {
+    if cfg!(windows) {
+        match codec {
+            VideoCodec::H264 => vec!["h264_nvenc", "h264_amf", "h264_qsv"],
+            VideoCodec::Hevc => vec!["hevc_nvenc", "hevc_amf", "hevc_qsv"],
+            VideoCodec::Vp9 => vec!["vp9_qsv"],
+            VideoCodec::Av1 => vec!["av1_nvenc", "av1_amf", "av1_qsv"],
+        }
+    } else if cfg!(target_os = "macos") {
+        match codec {
+            VideoCodec::H264 => vec!["h264_videotoolbox"],
+            VideoCodec::Hevc => vec!["hevc_videotoolbox"],
+            VideoCodec::Vp9 | VideoCodec::Av1 => Vec::new(),
+        }
+    } else if cfg!(target_os = "linux") {
+        match codec {
+            VideoCodec::H264 => vec!["h264_nvenc", "h264_qsv"],
+            VideoCodec::Hevc => vec!["hevc_nvenc", "hevc_qsv"],
+            VideoCodec::Vp9 => vec!["vp9_qsv"],
+            VideoCodec::Av1 => vec!["av1_nvenc", "av1_qsv"],
+        }
+    } else {
+        Vec::new()
+    }
+}
+
+/// 没有 GPU 时使用的软件编码器
+pub fn software_encoders(codec: VideoCodec) -> Vec<&'static str> {
+    match codec {
+        VideoCodec::H264 => vec!["libx264", "libopenh264"],
+        VideoCodec::Hevc => vec!["libx265"],
+        VideoCodec::Vp9 => vec!["libvpx-vp9"],
+        VideoCodec::Av1 => vec!["libsvtav1", "libaom-av1"],
+    }
+}
+
+/// 默认的硬件解码器
+///
+/// H.264 使用 `ob_codec.h` 中 `get_decoder()` 按 `decoders[]` 顺序选出的解码器，
+/// 其他格式使用 CUVID 与 QSV 解码器。
+pub fn hardware_decoders(codec: VideoCodec) -> Vec<String> {
+    let names: &[&str] = match codec {
+        VideoCodec::H264 => {
+            let decoder = unsafe { get_decoder() };
+            return if decoder.is_null() {
+                Vec::new()
+            } else {
+                vec![codec_name(decoder)]
+            };
+        }
+        VideoCodec::Hevc => &["hevc_cuvid", "hevc_qsv"],
+        VideoCodec::Vp9 => &["vp9_cuvid", "vp9_qsv"],
+        VideoCodec::Av1 => &["av1_cuvid", "av1_qsv"],
+    };
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+/// 软件解码器
+pub fn software_decoders(codec: VideoCodec) -> Vec<&'static str> {
+    match codec {
+        VideoCodec::H264 => vec!["h264"],
+        VideoCodec::Hevc => vec!["hevc"],
+        VideoCodec::Vp9 => vec!["vp9", "libvpx-vp9"],
+        VideoCodec::Av1 => vec!["libdav1d", "av1", "libaom-av1"],
+    }
+}
+
+/// 默认的候选顺序：先硬件，后软件
+pub fn default_encoders(codec: VideoCodec) -> Vec<&'static str> {
+    let mut candidates = hardware_encoders(codec);
+    candidates.extend(software_encoders(codec));
+    candidates
+}
+
+/// 列出 `codec_id` 所有可用的编码器
+///
+/// 每个编码器都以 640x360 和第一个软件像素格式实际打开一次，打不开的（例如缺少对应的 GPU）
+/// 和只接受硬件帧的（例如 VAAPI，[`Encoder`](crate::Encoder) 不上传硬件帧）不会出现在结果中。
+/// 结果按 `preference` 排序，未列出的排在后面并保持 FFmpeg 的注册顺序。
+/// 结果中的 [`CodecInfo::max_resolution`] 均为 `None`，需要另外调用 [`probe_max_resolution`]。
+pub fn probe_encoders<S: AsRef<str>>(codec_id: AVCodecID, preference: &[S]) -> Vec<CodecInfo> {
+    probe(CodecKind::Encoder, codec_id, preference)
+}
+
+/// 列出 `codec_id` 所有可用的解码器，排序规则同 [`probe_encoders`]
+pub fn probe_decoders<S: AsRef<str>>(codec_id: AVCodecID, preference: &[S]) -> Vec<CodecInfo> {
+    probe(CodecKind::Decoder, codec_id, preference)
+}
+
+/// 按 `preference` 中的名称顺序稳定排序
+pub fn sort_by_preference<S: AsRef<str>>(infos: &mut [CodecInfo], preference: &[S]) {
+    infos.sort_by_key(|info| {
+        preference
+            .iter()
+            .position(|name| name.as_ref() == info.name)
+            .unwrap_or(preference.len())
+    });
+}
+
+fn probe<S: AsRef<str>>(kind: CodecKind, codec_id: AVCodecID, preference: &[S]) -> Vec<CodecInfo> {
+    let mut infos = Vec::new();
+    let mut opaque: *mut c_void = ptr::null_mut();
+    loop {
+        let codec = unsafe { av_codec_iterate(&mut opaque) };
+        if codec.is_null() {
+            break;
+        }
+        let matches = unsafe {
+            let is_kind = match kind {
+                CodecKind::Encoder => av_codec_is_encoder(codec),
+                CodecKind::Decoder => av_codec_is_decoder(codec),
+            };
+            is_kind != 0
+                && (*codec).type_ == AVMediaType_AVMEDIA_TYPE_VIDEO
+                && (*codec).id == codec_id
+        };
+        if !matches || try_open(codec, kind, PROBE_SIZE).is_err() {
+            continue;
+        }
+        infos.push(CodecInfo {
+            name: codec_name(codec),
+            long_name: c_string(unsafe { (*codec).long_name }),
+            kind,
+            codec_id,
+            hardware: is_hardware(codec),
+            pixel_formats: pixel_formats(codec),
+            profiles: profiles(codec),
+            max_resolution: None,
+        });
+    }
+    sort_by_preference(&mut infos, preference);
+    infos
+}
+
+/// 探测编码器能打开的最大分辨率，写入 [`CodecInfo::max_resolution`]
+///
+/// 从 8K 到 1080p 逐级尝试，每个编码器最多再打开六次，硬件编码器每次都要初始化会话，
+/// 因此只在需要时对选中的编码器调用。都打不开时记为 `None`，不代表编码器只支持 640x360；
+/// 解码器不检查尺寸，同样保持 `None`。
+pub fn probe_max_resolution(info: &mut CodecInfo) {
+    if info.kind != CodecKind::Encoder {
+        return;
+    }
+    let Ok(name) = CString::new(info.name.as_str()) else {
+        return;
+    };
+    let codec = unsafe { avcodec_find_encoder_by_name(name.as_ptr()) };
+    if codec.is_null() {
+        return;
+    }
+    info.max_resolution = RESOLUTION_LADDER
+        .iter()
+        .copied()
+        .find(|&size| try_open(codec, CodecKind::Encoder, size).is_ok());
+}
+
+/// 用最小的参数打开一次上下文再释放，验证编解码器在本机可用
+fn try_open(
+    codec: *const AVCodec,
+    kind: CodecKind,
+    size: (i32, i32),
+) -> Result<(), Box<dyn Error>> {
+    let pix_fmt = match kind {
+        CodecKind::Encoder => software_pixel_format(codec).ok_or("only accepts hardware frames")?,
+        CodecKind::Decoder => AVPixelFormat_AV_PIX_FMT_NONE,
+    };
+    let mut ctx = unsafe { avcodec_alloc_context3(codec) };
+    if ctx.is_null() {
+        return Err("can not alloc codec context!".into());
+    }
+    let ret = unsafe {
+        if kind == CodecKind::Encoder {
+            (*ctx).width = size.0;
+            (*ctx).height = size.1;
+            (*ctx).pix_fmt = pix_fmt;
+            (*ctx).time_base = AVRational { num: 1, den: 30 };
+            (*ctx).framerate = AVRational { num: 30, den: 1 };
+            (*ctx).bit_rate = 1_000_000;
+        }
+        let ret = avcodec_open2(ctx, codec, ptr::null_mut());
+        avcodec_free_context(&mut ctx);
+        ret
+    };
+    check(ret, "avcodec_open2")?;
+    Ok(())
+}
+
+pub(crate) fn codec_name(codec: *const AVCodec) -> String {
+    c_string(unsafe { (*codec).name })
+}
+
+fn c_string(s: *const c_char) -> String {
+    if s.is_null() {
+        return String::new();
+    }
+    unsafe { CStr::from_ptr(s) }.to_string_lossy().into_owned()
+}
+
+fn is_hardware(codec: *const AVCodec) -> bool {
+    let capabilities = unsafe { (*codec).capabilities } as u32;
+    capabilities & (AV_CODEC_CAP_HARDWARE | AV_CODEC_CAP_HYBRID) != 0
+}
+
+/// 声明支持的像素格式
+pub(crate) fn pixel_formats(codec: *const AVCodec) -> Vec<AVPixelFormat> {
+    let mut formats = Vec::new();
+    let mut format = unsafe { (*codec).pix_fmts };
+    if format.is_null() {
+        return formats;
+    }
+    unsafe {
+        while *format != AVPixelFormat_AV_PIX_FMT_NONE {
+            formats.push(*format);
+            format = format.add(1);
+        }
+    }
+    formats
+}
+
+/// 第一个软件像素格式，跳过 VAAPI、VideoToolbox 等硬件表面格式；未声明格式的视为接受 YUV420P
+fn software_pixel_format(codec: *const AVCodec) -> Option<AVPixelFormat> {
+    let formats = pixel_formats(codec);
+    if formats.is_empty() {
+        return Some(AVPixelFormat_AV_PIX_FMT_YUV420P);
+    }
+    formats.into_iter().find(|&format| {
+        let desc = unsafe { av_pix_fmt_desc_get(format) };
+        desc.is_null() || unsafe { (*desc).flags } & AV_PIX_FMT_FLAG_HWACCEL as u64 == 0
+    })
+}
+
+/// 编码器是否接受 `pixel_format`，未声明支持列表的编码器视为接受
+pub(crate) fn supports_pixel_format(codec: *const AVCodec, pixel_format: AVPixelFormat) -> bool {
+    let formats = pixel_formats(codec);
+    formats.is_empty() || formats.contains(&pixel_format)
+}
+
+fn profiles(codec: *const AVCodec) -> Vec<Profile> {
+    let mut profiles = Vec::new();
+    let mut profile = unsafe { (*codec).profiles };
+    if profile.is_null() {
+        return profiles;
+    }
+    unsafe {
+        while (*profile).profile != PROFILE_UNKNOWN {
+            profiles.push(Profile {
+                id: (*profile).profile,
+                name: c_string((*profile).name),
+            });
+            profile = profile.add(1);
+        }
+    }
+    profiles
+}
@@ -0,0 +1,229 @@
+use crate::codec::{
+    codec_name, hardware_decoders, software_decoders, Rejection, SelectionError, VideoCodec,
+};
+use crate::encoder::StreamInfo;
+use crate::error::check;
+use crate::frame::VideoFrame;
+use crate::packet::EncodedPacket;
+use crate::{
+    av_mallocz, av_new_packet, av_packet_alloc, av_packet_free, av_packet_unref,
+    avcodec_alloc_context3, avcodec_find_decoder_by_name, avcodec_flush_buffers,
+    avcodec_free_context, avcodec_open2, avcodec_receive_frame, avcodec_send_packet,
+    AVCodecContext, AVPacket, AV_INPUT_BUFFER_PADDING_SIZE, AV_PKT_FLAG_KEY,
+};
+use std::error::Error;
+use std::ffi::CString;
+use std::ptr::{self, NonNull};
+
+/// 视频解码器
+///
+/// 持有 `AVCodecContext` 和复用的 `AVPacket`，`Drop` 时释放。输入为 [`Encoder`](crate::Encoder)
+/// 产出的 [`EncodedPacket`]，输出为拥有所有权的 [`VideoFrame`]。
+pub struct Decoder {
+    ctx: NonNull<AVCodecContext>,
+    packet: NonNull<AVPacket>,
+    codec_name: String,
+    video_codec: VideoCodec,
+    rejected: Vec<Rejection>,
+}
+
+// 解码上下文只通过 `&mut self` 访问，可以整体移动到解码线程
+unsafe impl Send for Decoder {}
+
+impl Decoder {
+    /// 打开 `codec` 的解码器，先尝试硬件解码器，再回退到软件解码器
+    pub fn new(codec: VideoCodec) -> Result<Self, Box<dyn Error>> {
+        Self::open(codec, &default_decoders(codec), &[])
+    }
+
+    /// 按编码输出的流描述打开解码器，带外的码流头 (`extradata`) 会一并传给解码器
+    pub fn from_stream_info(info: &StreamInfo) -> Result<Self, Box<dyn Error>> {
+        Self::open(info.codec, &default_decoders(info.codec), &info.extradata)
+    }
+
+    /// 按给定顺序尝试解码器
+    pub fn with_preference<S: AsRef<str>>(
+        codec: VideoCodec,
+        names: &[S],
+    ) -> Result<Self, Box<dyn Error>> {
+        let names: Vec<String> = names.iter().map(|n| n.as_ref().to_string()).collect();
+        Self::open(codec, &names, &[])
+    }
+
+    /// 实际使用的解码器名称
+    pub fn codec_name(&self) -> &str {
+        &self.codec_name
+    }
+
+    pub fn video_codec(&self) -> VideoCodec {
+        self.video_codec
+    }
+
+    /// 选出当前解码器之前被跳过的候选及原因
+    pub fn rejected(&self) -> &[Rejection] {
+        &self.rejected
+    }
+
+    /// 解码一个数据包，返回当前已经输出的帧（可能为空）
+    pub fn decode(&mut self, packet: &EncodedPacket) -> Result<Vec<VideoFrame>, Box<dyn Error>> {
+        if packet.codec_id != self.video_codec.id() {
+            return Err(format!(
+                "packet codec id {} does not match decoder {:?}",
+                packet.codec_id, self.video_codec
+            )
+            .into());
+        }
+        if packet.is_empty() {
+            return Ok(Vec::new());
+        }
+        let size = i32::try_from(packet.len())?;
+        unsafe {
+            let raw = self.packet.as_ptr();
+            check(av_new_packet(raw, size), "av_new_packet")?;
+            ptr::copy_nonoverlapping(packet.data.as_ptr(), (*raw).data, packet.len());
+            (*raw).pts = packet.pts;
+            (*raw).dts = packet.dts;
+            (*raw).duration = packet.duration;
+            if packet.keyframe {
+                (*raw).flags |= AV_PKT_FLAG_KEY as i32;
+            }
+            let ret = avcodec_send_packet(self.ctx.as_ptr(), raw);
+            av_packet_unref(raw);
+            check(ret, "avcodec_send_packet")?;
+        }
+        self.receive_frames()
+    }
+
+    /// 取出解码器中缓存的所有帧，之后可以继续解码新的数据包
+    pub fn flush(&mut self) -> Result<Vec<VideoFrame>, Box<dyn Error>> {
+        check(
+            unsafe { avcodec_send_packet(self.ctx.as_ptr(), ptr::null()) },
+            "avcodec_send_packet",
+        )?;
+        let frames = self.receive_frames()?;
+        self.reset();
+        Ok(frames)
+    }
+
+    /// 丢弃缓存的数据包和帧，用于丢包或跳转后从下一个关键帧重新开始
+    pub fn reset(&mut self) {
+        unsafe { avcodec_flush_buffers(self.ctx.as_ptr()) }
+    }
+
+    /// 依次尝试候选解码器，返回第一个能打开的
+    fn open(
+        codec: VideoCodec,
+        candidates: &[String],
+        extradata: &[u8],
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut rejected = Vec::new();
+        for name in candidates {
+            match Self::open_by_name(codec, name, extradata) {
+                Ok(mut decoder) => {
+                    decoder.rejected = rejected;
+                    return Ok(decoder);
+                }
+                Err(reason) => rejected.push(Rejection {
+                    codec: name.clone(),
+                    reason: reason.to_string(),
+                }),
+            }
+        }
+        Err(SelectionError { rejected }.into())
+    }
+
+    fn open_by_name(
+        video_codec: VideoCodec,
+        name: &str,
+        extradata: &[u8],
+    ) -> Result<Self, Box<dyn Error>> {
+        let c_name = CString::new(name)?;
+        let codec = unsafe { avcodec_find_decoder_by_name(c_name.as_ptr()) };
+        if codec.is_null() {
+            return Err("not available in this FFmpeg build".into());
+        }
+        let codec_id = unsafe { (*codec).id };
+        if codec_id != video_codec.id() {
+            return Err(
+                format!("decodes codec id {}, expected {:?}", codec_id, video_codec).into(),
+            );
+        }
+
+        let ctx = NonNull::new(unsafe { avcodec_alloc_context3(codec) })
+            .ok_or("can not alloc codec context!")?;
+        let packet = match NonNull::new(unsafe { av_packet_alloc() }) {
+            Some(packet) => packet,
+            None => {
+                let mut ctx = ctx.as_ptr();
+                unsafe { avcodec_free_context(&mut ctx) };
+                return Err("can not alloc packet!".into());
+            }
+        };
+        // 先交给 Decoder 持有，后续打开失败时由 Drop 统一释放
+        let decoder = Decoder {
+            ctx,
+            packet,
+            codec_name: codec_name(codec),
+            video_codec,
+            rejected: Vec::new(),
+        };
+
+        unsafe {
+            let ctx = decoder.ctx.as_ptr();
+            if !extradata.is_empty() {
+                // extradata 由 avcodec_free_context 释放，必须用 av_malloc 分配并带填充
+                let size = extradata.len() + AV_INPUT_BUFFER_PADDING_SIZE as usize;
+                let buffer = av_mallocz(size) as *mut u8;
+                if buffer.is_null() {
+                    return Err("can not alloc extradata!".into());
+                }
+                ptr::copy_nonoverlapping(extradata.as_ptr(), buffer, extradata.len());
+                (*ctx).extradata = buffer;
+                (*ctx).extradata_size = i32::try_from(extradata.len())?;
+            }
+            check(avcodec_open2(ctx, codec, ptr::null_mut()), "avcodec_open2")?;
+        }
+        Ok(decoder)
+    }
+
+    /// 循环读取帧，直到解码器需要更多输入或已经结束
+    fn receive_frames(&mut self) -> Result<Vec<VideoFrame>, Box<dyn Error>> {
+        let mut frames = Vec::new();
+        loop {
+            let frame = VideoFrame::empty()?;
+            let ret = unsafe { avcodec_receive_frame(self.ctx.as_ptr(), frame.as_ptr()) };
+            match check(ret, "avcodec_receive_frame") {
+                Ok(_) => frames.push(frame),
+                Err(err) if err.is_eagain() || err.is_eof() => return Ok(frames),
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+}
+
+impl Drop for Decoder {
+    fn drop(&mut self) {
+        let mut packet = self.packet.as_ptr();
+        let mut ctx = self.ctx.as_ptr();
+        unsafe {
+            av_packet_free(&mut packet);
+            avcodec_free_context(&mut ctx);
+        }
+    }
+}
+
+impl std::fmt::Debug for Decoder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Decoder")
+            .field("codec", &self.codec_name)
+            .field("video_codec", &self.video_codec)
+            .finish()
+    }
+}
+
+/// 默认的候选顺序：先硬件，后软件
+fn default_decoders(codec: VideoCodec) -> Vec<String> {
+    let mut candidates = hardware_decoders(codec);
+    candidates.extend(software_decoders(codec).into_iter().map(String::from));
+    candidates
+}
@@ -0,0 +1,563 @@
+use crate::codec::{
+    codec_name, default_encoders, supports_pixel_format, BitstreamFormat, Rejection,
+    SelectionError, VideoCodec,
+};
+use crate::error::{check, AvError};
+use crate::frame::{VideoFrame, AV_NOPTS_VALUE};
+use crate::packet::EncodedPacket;
+use crate::rate_control::{RateControl, RateMode};
+use crate::{
+    av_opt_set, av_packet_alloc, av_packet_free, av_packet_unref, avcodec_alloc_context3,
+    avcodec_find_encoder_by_name, avcodec_free_context, avcodec_open2, avcodec_receive_packet,
+    avcodec_send_frame, AVCodecContext, AVCodecID, AVPacket, AVPictureType_AV_PICTURE_TYPE_I,
+    AVPictureType_AV_PICTURE_TYPE_NONE, AVPixelFormat, AVPixelFormat_AV_PIX_FMT_YUV420P,
+    AVRational, AV_CODEC_FLAG_GLOBAL_HEADER, AV_OPT_SEARCH_CHILDREN,
+};
+use std::error::Error;
+use std::ffi::CString;
+use std::ptr::{self, NonNull};
+
+/// 输出时间戳的时钟频率，与 RTP 视频时钟一致；帧率改变时无需更换时间基
+const TIME_BASE_DEN: i32 = 90_000;
+
+/// 编码器参数
+#[derive(Debug, Clone, PartialEq)]
+pub struct EncoderBuilder {
+    video_codec: VideoCodec,
+    candidates: Option<Vec<String>>,
+    width: i32,
+    height: i32,
+    fps: i32,
+    rate_control: RateControl,
+    gop: i32,
+    pixel_format: AVPixelFormat,
+    global_header: bool,
+}
+
+impl Default for EncoderBuilder {
+    fn default() -> Self {
+        Self {
+            video_codec: VideoCodec::H264,
+            candidates: None,
+            width: 0,
+            height: 0,
+            fps: 30,
+            rate_control: RateControl::default(),
+            gop: 60,
+            pixel_format: AVPixelFormat_AV_PIX_FMT_YUV420P,
+            global_header: false,
+        }
+    }
+}
+
+impl EncoderBuilder {
+    /// 编码格式，默认 H.264；决定自动选择时的候选编码器
+    pub fn video_codec(mut self, video_codec: VideoCodec) -> Self {
+        self.video_codec = video_codec;
+        self
+    }
+
+    /// 按名称指定编码器，例如 `"h264_nvenc"`，必须是 [`EncoderBuilder::video_codec`] 格式的编码器；
+    /// 不指定时自动选择
+    pub fn codec(mut self, name: &str) -> Self {
+        self.candidates = Some(vec![name.to_string()]);
+        self
+    }
+
+    /// 按给定顺序尝试编码器，替代默认的“先硬件后软件”顺序
+    pub fn preference<S: AsRef<str>>(mut self, names: &[S]) -> Self {
+        self.candidates = Some(names.iter().map(|n| n.as_ref().to_string()).collect());
+        self
+    }
+
+    pub fn size(mut self, width: i32, height: i32) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    /// 帧率，决定未设置时间戳的帧之间的间隔
+    pub fn fps(mut self, fps: i32) -> Self {
+        self.fps = fps;
+        self
+    }
+
+    /// 目标码率 (bit/s)
+    pub fn bitrate(mut self, bitrate: i64) -> Self {
+        self.rate_control.mode = RateMode::Vbr {
+            bitrate,
+            max_bitrate: None,
+        };
+        self
+    }
+
+    /// 码率控制方式，会覆盖 [`EncoderBuilder::bitrate`]
+    pub fn rate_control(mut self, rate_control: RateControl) -> Self {
+        self.rate_control = rate_control;
+        self
+    }
+
+    /// 关键帧间隔（帧数）
+    pub fn gop(mut self, gop: i32) -> Self {
+        self.gop = gop;
+        self
+    }
+
+    /// 输入帧的像素格式，应与 `ObStream` 的目标格式一致；QSV 编码器需要 NV12
+    pub fn pixel_format(mut self, pixel_format: AVPixelFormat) -> Self {
+        self.pixel_format = pixel_format;
+        self
+    }
+
+    /// 参数集等码流头放在 [`StreamInfo::extradata`] 中而不是关键帧前，MP4 等容器需要
+    pub fn global_header(mut self, global_header: bool) -> Self {
+        self.global_header = global_header;
+        self
+    }
+
+    /// 依次尝试候选编码器，返回第一个能打开的
+    ///
+    /// 指定了 [`EncoderBuilder::codec`] 或 [`EncoderBuilder::preference`] 时只尝试给定的编码器；
+    /// 否则先尝试当前平台的硬件编码器，再回退到软件编码器。每个被跳过的候选及原因记录在 [`Encoder::rejected`] 中，
+    /// 全部失败时返回 [`SelectionError`]。
+    pub fn build(self) -> Result<Encoder, Box<dyn Error>> {
+        if self.width <= 0 || self.height <= 0 {
+            return Err(format!("invalid encoder size {}x{}", self.width, self.height).into());
+        }
+        if self.fps <= 0 {
+            return Err(format!("invalid frame rate {}", self.fps).into());
+        }
+        self.rate_control.validate()?;
+
+        let candidates = match &self.candidates {
+            Some(names) => names.clone(),
+            None => default_encoders(self.video_codec)
+                .into_iter()
+                .map(String::from)
+                .collect(),
+        };
+        let mut rejected = Vec::new();
+        for name in candidates {
+            match self.open(&name) {
+                Ok(mut encoder) => {
+                    encoder.rejected = rejected;
+                    return Ok(encoder);
+                }
+                Err(reason) => rejected.push(Rejection {
+                    codec: name,
+                    reason: reason.to_string(),
+                }),
+            }
+        }
+        Err(SelectionError { rejected }.into())
+    }
+
+    /// 按名称查找并打开一个编码器
+    fn open(&self, name: &str) -> Result<Encoder, Box<dyn Error>> {
+        let c_name = CString::new(name)?;
+        let codec = unsafe { avcodec_find_encoder_by_name(c_name.as_ptr()) };
+        if codec.is_null() {
+            return Err("not available in this FFmpeg build".into());
+        }
+        let codec_id = unsafe { (*codec).id };
+        if codec_id != self.video_codec.id() {
+            return Err(format!(
+                "encodes codec id {}, expected {:?}",
+                codec_id, self.video_codec
+            )
+            .into());
+        }
+        if !supports_pixel_format(codec, self.pixel_format) {
+            return Err(format!("pixel format {} is not supported", self.pixel_format).into());
+        }
+
+        let ctx = NonNull::new(unsafe { avcodec_alloc_context3(codec) })
+            .ok_or("can not alloc codec context!")?;
+        let packet = match NonNull::new(unsafe { av_packet_alloc() }) {
+            Some(packet) => packet,
+            None => {
+                let mut ctx = ctx.as_ptr();
+                unsafe { avcodec_free_context(&mut ctx) };
+                return Err("can not alloc packet!".into());
+            }
+        };
+        // 先交给 Encoder 持有，后续打开失败时由 Drop 统一释放
+        let encoder = Encoder {
+            ctx,
+            packet,
+            codec_name: codec_name(codec),
+            video_codec: self.video_codec,
+            rejected: Vec::new(),
+            rate_control: self.rate_control,
+            fps: self.fps,
+            open_fps: self.fps,
+            next_pts: 0,
+            sequence: 0,
+            force_keyframe: false,
+            flushed: false,
+        };
+
+        unsafe {
+            let ctx = encoder.ctx.as_ptr();
+            (*ctx).width = self.width;
+            (*ctx).height = self.height;
+            (*ctx).pix_fmt = self.pixel_format;
+            (*ctx).time_base = AVRational {
+                num: 1,
+                den: TIME_BASE_DEN,
+            };
+            (*ctx).framerate = AVRational {
+                num: self.fps,
+                den: 1,
+            };
+            self.rate_control.apply(ctx, &encoder.codec_name)?;
+            (*ctx).gop_size = self.gop;
+            // 屏幕共享要求低延迟，不使用 B 帧
+            (*ctx).max_b_frames = 0;
+            if self.global_header {
+                (*ctx).flags |= AV_CODEC_FLAG_GLOBAL_HEADER as i32;
+            }
+            // x264/x265/NVENC/QSV 默认把强制的 I 帧编码为普通 I 帧，需要显式要求 IDR
+            let forced_idr = match encoder.codec_name.as_str() {
+                "libx264" | "libx265" => Some(c"forced-idr"),
+                name if name.ends_with("_nvenc") => Some(c"forced-idr"),
+                name if name.ends_with("_qsv") => Some(c"forced_idr"),
+                _ => None,
+            };
+            if let Some(key) = forced_idr {
+                check(
+                    av_opt_set(
+                        ctx.cast(),
+                        key.as_ptr(),
+                        c"1".as_ptr(),
+                        AV_OPT_SEARCH_CHILDREN as i32,
+                    ),
+                    "av_opt_set",
+                )?;
+            }
+            check(avcodec_open2(ctx, codec, ptr::null_mut()), "avcodec_open2")?;
+        }
+        Ok(encoder)
+    }
+}
+
+/// 编码输出流的描述
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamInfo {
+    pub codec: VideoCodec,
+    pub width: i32,
+    pub height: i32,
+    /// 时间戳的时间基 (num, den)
+    pub time_base: (i32, i32),
+    /// 编码器报告的档次与级别，未知时为负数
+    pub profile: i32,
+    pub level: i32,
+    /// 码流头：H.264/HEVC 为 avcC/hvcC 或 Annex B 参数集，AV1 为 av1C，
+    /// 只有开启 [`EncoderBuilder::global_header`] 时编码器才会填写
+    pub extradata: Vec<u8>,
+}
+
+impl StreamInfo {
+    pub fn mime_type(&self) -> &'static str {
+        self.codec.mime_type()
+    }
+
+    pub fn bitstream_format(&self) -> BitstreamFormat {
+        self.codec.bitstream_format()
+    }
+}
+
+/// 视频编码器
+///
+/// 持有 `AVCodecContext` 和复用的 `AVPacket`，`Drop` 时释放。输入帧通常来自
+/// [`ObStream::read_frame`](crate::ObStream::read_frame)。
+pub struct Encoder {
+    ctx: NonNull<AVCodecContext>,
+    packet: NonNull<AVPacket>,
+    codec_name: String,
+    video_codec: VideoCodec,
+    rejected: Vec<Rejection>,
+    rate_control: RateControl,
+    fps: i32,
+    /// 打开编码器时的帧率，libx264 和 NVENC 始终按它给每帧分配码率
+    open_fps: i32,
+    next_pts: i64,
+    sequence: u64,
+    force_keyframe: bool,
+    flushed: bool,
+}
+
+// 编码上下文只通过 `&mut self` 访问，可以整体移动到编码线程
+unsafe impl Send for Encoder {}
+
+impl Encoder {
+    pub fn builder() -> EncoderBuilder {
+        EncoderBuilder::default()
+    }
+
+    /// 实际使用的编码器名称
+    pub fn codec_name(&self) -> &str {
+        &self.codec_name
+    }
+
+    pub fn video_codec(&self) -> VideoCodec {
+        self.video_codec
+    }
+
+    /// 码流参数，供复用器和信令描述输出流
+    pub fn stream_info(&self) -> StreamInfo {
+        let ctx = self.as_ref();
+        let extradata = if ctx.extradata.is_null() || ctx.extradata_size <= 0 {
+            Vec::new()
+        } else {
+            unsafe { std::slice::from_raw_parts(ctx.extradata, ctx.extradata_size as usize) }
+                .to_vec()
+        };
+        StreamInfo {
+            codec: self.video_codec,
+            width: ctx.width,
+            height: ctx.height,
+            time_base: (ctx.time_base.num, ctx.time_base.den),
+            profile: ctx.profile,
+            level: ctx.level,
+            extradata,
+        }
+    }
+
+    /// 选出当前编码器之前被跳过的候选及原因
+    pub fn rejected(&self) -> &[Rejection] {
+        &self.rejected
+    }
+
+    pub fn width(&self) -> i32 {
+        self.as_ref().width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.as_ref().height
+    }
+
+    pub fn pixel_format(&self) -> AVPixelFormat {
+        self.as_ref().pix_fmt
+    }
+
+    pub fn codec_id(&self) -> AVCodecID {
+        self.as_ref().codec_id
+    }
+
+    /// 输出数据包时间戳的时间基，固定为 1/90000
+    pub fn time_base(&self) -> AVRational {
+        self.as_ref().time_base
+    }
+
+    pub fn fps(&self) -> i32 {
+        self.fps
+    }
+
+    pub fn rate_control(&self) -> &RateControl {
+        &self.rate_control
+    }
+
+    /// 修改目标码率，从下一帧开始生效，不重新打开编码器
+    ///
+    /// VBR 的峰值码率按相同比例缩放。恒定质量模式没有目标码率，返回错误；
+    /// 只有 libx264、NVENC 和 QSV 在编码过程中读取新的码率，其他编码器返回错误。
+    pub fn set_bitrate(&mut self, bitrate: i64) -> Result<(), Box<dyn Error>> {
+        self.check_reconfigurable()?;
+        let mut rate_control = self.rate_control;
+        rate_control.mode = match rate_control.mode {
+            RateMode::Cbr { .. } => RateMode::Cbr { bitrate },
+            RateMode::Vbr {
+                bitrate: old,
+                max_bitrate,
+            } => RateMode::Vbr {
+                bitrate,
+                max_bitrate: max_bitrate
+                    .map(|max| (max as f64 * bitrate as f64 / old as f64) as i64),
+            },
+            RateMode::Crf(_) | RateMode::Cqp(_) => {
+                return Err("bitrate can not be changed in constant quality mode".into());
+            }
+        };
+        rate_control.validate_for(&self.codec_name)?;
+
+        self.write_rate(&rate_control, self.fps)?;
+        self.rate_control = rate_control;
+        Ok(())
+    }
+
+    /// 修改帧率，从下一帧开始生效，不重新打开编码器
+    ///
+    /// 时间基固定为 1/90000，未设置时间戳的帧之间的间隔随之改变。QSV 直接更新编码器的帧率；
+    /// libx264 (`b_vfr_input=0`) 和 NVENC 在编码过程中不读取新的帧率，仍按打开时的帧率给每帧
+    /// 分配码率，因此按帧率比例换算写入上下文的码率，使实际输出码率保持为 [`Encoder::rate_control`]
+    /// 中的目标值。恒定质量模式与帧率无关，只改变时间戳。其他编码器返回错误。
+    pub fn set_fps(&mut self, fps: i32) -> Result<(), Box<dyn Error>> {
+        self.check_reconfigurable()?;
+        if fps <= 0 {
+            return Err(format!("invalid frame rate {}", fps).into());
+        }
+        let rate_control = self.rate_control;
+        self.write_rate(&rate_control, fps)?;
+        self.fps = fps;
+        Ok(())
+    }
+
+    /// 把码率设置写入已打开的编码上下文，`fps` 为实际输入帧率
+    fn write_rate(&mut self, rate_control: &RateControl, fps: i32) -> Result<(), Box<dyn Error>> {
+        let settings = rate_control.settings(&self.codec_name);
+        let qsv = self.codec_name.ends_with("_qsv");
+        // 编码器认为的每帧码率为 bit_rate / open_fps，需要等于 目标码率 / fps；
+        // VBV 缓冲区按比特计，不随帧率换算
+        let open_fps = self.open_fps as i64;
+        let scale = |rate: i64| {
+            if qsv {
+                rate
+            } else {
+                rate * open_fps / fps as i64
+            }
+        };
+        unsafe {
+            let ctx = self.ctx.as_ptr();
+            (*ctx).bit_rate = scale(settings.bit_rate);
+            (*ctx).rc_max_rate = scale(settings.rc_max_rate);
+            (*ctx).rc_min_rate = scale(settings.rc_min_rate);
+            if let Some(size) = settings.rc_buffer_size {
+                (*ctx).rc_buffer_size = i32::try_from(size)?;
+            }
+            if qsv {
+                (*ctx).framerate = AVRational { num: fps, den: 1 };
+            }
+        }
+        Ok(())
+    }
+
+    /// 写入编码上下文的目标码率，帧率改变后与 [`Encoder::rate_control`] 不同
+    #[cfg(test)]
+    pub(crate) fn context_bit_rate(&self) -> i64 {
+        self.as_ref().bit_rate
+    }
+
+    /// 运行时调整参数需要编码器在编码过程中重新读取上下文
+    fn check_reconfigurable(&self) -> Result<(), Box<dyn Error>> {
+        let name = self.codec_name.as_str();
+        if name == "libx264" || name.ends_with("_nvenc") || name.ends_with("_qsv") {
+            Ok(())
+        } else {
+            Err(format!("{} does not support reconfiguration while encoding", name).into())
+        }
+    }
+
+    /// 要求下一帧编码为 IDR 帧，用于新观众加入或丢包恢复
+    pub fn force_keyframe(&mut self) {
+        self.force_keyframe = true;
+    }
+
+    /// 编码一帧，返回当前已经产出的数据包（可能为空）
+    ///
+    /// 时间戳单位为 1/90000 秒，未设置或没有递增的时间戳按当前帧率顺延。
+    pub fn encode(&mut self, frame: &mut VideoFrame) -> Result<Vec<EncodedPacket>, Box<dyn Error>> {
+        if self.flushed {
+            return Err("encoder has been flushed".into());
+        }
+        if (frame.width(), frame.height(), frame.format())
+            != (self.width(), self.height(), self.pixel_format())
+        {
+            return Err(format!(
+                "frame is {}x{} format {}, encoder expects {}x{} format {}",
+                frame.width(),
+                frame.height(),
+                frame.format(),
+                self.width(),
+                self.height(),
+                self.pixel_format()
+            )
+            .into());
+        }
+        if frame.pts() == AV_NOPTS_VALUE || frame.pts() < self.next_pts {
+            frame.set_pts(self.next_pts);
+        }
+        self.next_pts = frame.pts() + (TIME_BASE_DEN / self.fps).max(1) as i64;
+        // 输入帧可能来自解码器并带有 I 帧标记，总是显式设置帧类型
+        frame.set_pict_type(if self.force_keyframe {
+            AVPictureType_AV_PICTURE_TYPE_I
+        } else {
+            AVPictureType_AV_PICTURE_TYPE_NONE
+        });
+
+        check(
+            unsafe { avcodec_send_frame(self.ctx.as_ptr(), frame.as_ptr()) },
+            "avcodec_send_frame",
+        )?;
+        self.force_keyframe = false;
+        Ok(self.receive_packets()?)
+    }
+
+    /// 结束编码，取出编码器中缓存的所有数据包；之后不能再调用 [`Encoder::encode`]
+    pub fn flush(&mut self) -> Result<Vec<EncodedPacket>, Box<dyn Error>> {
+        if self.flushed {
+            return Ok(Vec::new());
+        }
+        self.flushed = true;
+        check(
+            unsafe { avcodec_send_frame(self.ctx.as_ptr(), ptr::null()) },
+            "avcodec_send_frame",
+        )?;
+        Ok(self.receive_packets()?)
+    }
+
+    /// 原始指针，仅供 crate 内部与 C 接口交互
+    pub(crate) fn as_ptr(&self) -> *mut AVCodecContext {
+        self.ctx.as_ptr()
+    }
+
+    fn as_ref(&self) -> &AVCodecContext {
+        unsafe { self.ctx.as_ref() }
+    }
+
+    /// 循环读取数据包，直到编码器需要更多输入或已经结束
+    fn receive_packets(&mut self) -> Result<Vec<EncodedPacket>, AvError> {
+        let mut packets = Vec::new();
+        loop {
+            let ret = unsafe { avcodec_receive_packet(self.as_ptr(), self.packet.as_ptr()) };
+            if let Err(err) = check(ret, "avcodec_receive_packet") {
+                if err.is_eagain() || err.is_eof() {
+                    return Ok(packets);
+                }
+                return Err(err);
+            }
+            unsafe {
+                let codec_id = self.as_ref().codec_id;
+                packets.push(EncodedPacket::copy_from(
+                    self.packet.as_ref(),
+                    codec_id,
+                    self.sequence,
+                ));
+                self.sequence += 1;
+                av_packet_unref(self.packet.as_ptr());
+            }
+        }
+    }
+}
+
+impl Drop for Encoder {
+    fn drop(&mut self) {
+        let mut packet = self.packet.as_ptr();
+        let mut ctx = self.ctx.as_ptr();
+        unsafe {
+            av_packet_free(&mut packet);
+            avcodec_free_context(&mut ctx);
+        }
+    }
+}
+
+impl std::fmt::Debug for Encoder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Encoder")
+            .field("codec", &self.codec_name)
+            .field("width", &self.width())
+            .field("height", &self.height())
+            .field("pixel_format", &self.pixel_format())
+            .finish()
+    }
+}
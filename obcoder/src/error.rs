@@ -0,0 +1,62 @@
+use crate::av_strerror;
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_int};
+
+/// `AVERROR(EAGAIN)`，bindgen 不会展开函数式宏
+pub(crate) const AVERROR_EAGAIN: c_int = -libc::EAGAIN;
+/// `AVERROR_EOF`，即 `FFERRTAG('E', 'O', 'F', ' ')`
+pub(crate) const AVERROR_EOF: c_int =
+    -((b'E' as c_int) | (b'O' as c_int) << 8 | (b'F' as c_int) << 16 | (b' ' as c_int) << 24);
+
+/// FFmpeg 函数返回的错误码
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AvError {
+    code: c_int,
+    context: String,
+}
+
+impl AvError {
+    /// `context` 说明出错的操作，例如 `"avcodec_open2"`
+    pub fn new(code: c_int, context: impl Into<String>) -> Self {
+        Self {
+            code,
+            context: context.into(),
+        }
+    }
+
+    /// 负数形式的 `AVERROR` 错误码
+    pub fn code(&self) -> c_int {
+        self.code
+    }
+
+    pub fn is_eagain(&self) -> bool {
+        self.code == AVERROR_EAGAIN
+    }
+
+    pub fn is_eof(&self) -> bool {
+        self.code == AVERROR_EOF
+    }
+}
+
+impl std::fmt::Display for AvError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut buffer = [0 as c_char; 128];
+        let ret = unsafe { av_strerror(self.code, buffer.as_mut_ptr(), buffer.len()) };
+        if ret < 0 {
+            return write!(f, "{} failed: error {}", self.context, self.code);
+        }
+        let message = unsafe { CStr::from_ptr(buffer.as_ptr()) }.to_string_lossy();
+        write!(f, "{} failed: {} ({})", self.context, message, self.code)
+    }
+}
+
+impl std::error::Error for AvError {}
+
+/// 把 FFmpeg 的返回值转换为 `Result`，非负值原样返回
+pub(crate) fn check(ret: c_int, context: &str) -> Result<c_int, AvError> {
+    if ret < 0 {
+        Err(AvError::new(ret, context))
+    } else {
+        Ok(ret)
+    }
+}
@@ -0,0 +1,185 @@
+use crate::{
+    av_frame_alloc, av_frame_free, av_frame_get_buffer, av_frame_make_writable,
+    av_image_fill_plane_sizes, av_pix_fmt_count_planes, AVFrame, AVPictureType, AVPixelFormat,
+};
+use std::error::Error;
+use std::ptr::NonNull;
+
+/// `AV_NOPTS_VALUE`，表示未设置时间戳
+pub(crate) const AV_NOPTS_VALUE: i64 = i64::MIN;
+/// 图像最多的平面数（`av_image_fill_plane_sizes` 只处理前 4 个）
+const MAX_PLANES: usize = 4;
+
+/// 拥有所有权的视频帧
+///
+/// 包装一个 `av_frame_alloc` 分配的 `AVFrame`，`Drop` 时通过 `av_frame_free`
+/// 释放引用的缓冲区和结构体本身，调用方不再需要手动 `av_frame_unref`。
+pub struct VideoFrame {
+    raw: NonNull<AVFrame>,
+}
+
+// 帧数据由引用计数缓冲区持有，引用计数是原子的，可以移动到其他线程
+unsafe impl Send for VideoFrame {}
+
+impl VideoFrame {
+    /// 分配一个空帧，不包含图像数据
+    pub(crate) fn empty() -> Result<Self, Box<dyn Error>> {
+        let raw = unsafe { av_frame_alloc() };
+        NonNull::new(raw)
+            .map(|raw| Self { raw })
+            .ok_or_else(|| "can not alloc frame!".into())
+    }
+
+    /// 分配 `width`x`height`、`format` 格式的帧，数据内容未初始化
+    pub fn new(width: i32, height: i32, format: AVPixelFormat) -> Result<Self, Box<dyn Error>> {
+        if width <= 0 || height <= 0 {
+            return Err(format!("invalid frame size {}x{}", width, height).into());
+        }
+        let frame = Self::empty()?;
+        let ret = unsafe {
+            let raw = frame.raw.as_ptr();
+            (*raw).width = width;
+            (*raw).height = height;
+            (*raw).format = format;
+            av_frame_get_buffer(raw, 0)
+        };
+        if ret < 0 {
+            return Err(format!("can not alloc frame buffer: {}", ret).into());
+        }
+        Ok(frame)
+    }
+
+    pub fn width(&self) -> i32 {
+        self.as_ref().width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.as_ref().height
+    }
+
+    pub fn format(&self) -> AVPixelFormat {
+        self.as_ref().format
+    }
+
+    pub fn pts(&self) -> i64 {
+        self.as_ref().pts
+    }
+
+    pub fn set_pts(&mut self, pts: i64) {
+        unsafe { (*self.raw.as_ptr()).pts = pts }
+    }
+
+    /// 设置帧类型，编码时 `AV_PICTURE_TYPE_I` 表示强制关键帧
+    pub(crate) fn set_pict_type(&mut self, pict_type: AVPictureType) {
+        unsafe { (*self.raw.as_ptr()).pict_type = pict_type }
+    }
+
+    /// 各平面每行的字节数，未使用的平面为 0
+    pub fn linesizes(&self) -> [i32; MAX_PLANES] {
+        let mut linesizes = [0; MAX_PLANES];
+        linesizes.copy_from_slice(&self.as_ref().linesize[..MAX_PLANES]);
+        linesizes
+    }
+
+    /// 有数据的平面数，不超过像素格式本身的平面数
+    pub fn plane_count(&self) -> usize {
+        let format_planes = unsafe { av_pix_fmt_count_planes(self.format()) };
+        let format_planes = (format_planes.max(0) as usize).min(MAX_PLANES);
+        self.as_ref().data[..format_planes]
+            .iter()
+            .take_while(|data| !data.is_null())
+            .count()
+    }
+
+    /// 第 `index` 个平面的数据，长度为 `linesize * 平面行数`
+    ///
+    /// `index` 不小于 [`plane_count`](Self::plane_count) 时返回 `None`。
+    pub fn plane(&self, index: usize) -> Option<&[u8]> {
+        if index >= self.plane_count() {
+            return None;
+        }
+        let size = self.plane_sizes()[index];
+        if size == 0 {
+            return Some(&[]);
+        }
+        Some(unsafe { std::slice::from_raw_parts(self.as_ref().data[index], size) })
+    }
+
+    /// 第 `index` 个平面的可写数据
+    ///
+    /// 缓冲区被其他帧共享时先复制一份，保证写入不会影响其他引用。
+    pub fn plane_mut(&mut self, index: usize) -> Result<&mut [u8], Box<dyn Error>> {
+        if index >= self.plane_count() {
+            return Err(format!("plane {} out of range", index).into());
+        }
+        let ret = unsafe { av_frame_make_writable(self.raw.as_ptr()) };
+        if ret < 0 {
+            return Err(format!("can not make frame writable: {}", ret).into());
+        }
+        let size = self.plane_sizes()[index];
+        if size == 0 {
+            return Ok(&mut []);
+        }
+        Ok(unsafe { std::slice::from_raw_parts_mut(self.as_ref().data[index], size) })
+    }
+
+    /// 所有有数据的平面
+    pub fn planes(&self) -> Vec<&[u8]> {
+        (0..self.plane_count())
+            .filter_map(|i| self.plane(i))
+            .collect()
+    }
+
+    /// 原始指针，仅供 crate 内部与 C 接口交互
+    pub(crate) fn as_ptr(&self) -> *mut AVFrame {
+        self.raw.as_ptr()
+    }
+
+    fn as_ref(&self) -> &AVFrame {
+        unsafe { self.raw.as_ref() }
+    }
+
+    fn plane_sizes(&self) -> [usize; MAX_PLANES] {
+        let frame = self.as_ref();
+        let mut sizes = [0usize; MAX_PLANES];
+        let mut linesizes = [0isize; MAX_PLANES];
+        for (i, linesize) in linesizes.iter_mut().enumerate() {
+            // 负的行宽表示倒置存储，不作为连续切片暴露
+            if frame.data[i].is_null() || frame.linesize[i] <= 0 {
+                continue;
+            }
+            *linesize = frame.linesize[i] as isize;
+        }
+        let ret = unsafe {
+            av_image_fill_plane_sizes(
+                sizes.as_mut_ptr(),
+                frame.format,
+                frame.height,
+                linesizes.as_ptr(),
+            )
+        };
+        if ret < 0 {
+            return [0; MAX_PLANES];
+        }
+        sizes
+    }
+}
+
+impl Drop for VideoFrame {
+    fn drop(&mut self) {
+        let mut raw = self.raw.as_ptr();
+        unsafe { av_frame_free(&mut raw) }
+    }
+}
+
+impl std::fmt::Debug for VideoFrame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VideoFrame")
+            .field("width", &self.width())
+            .field("height", &self.height())
+            .field("format", &self.format())
+            .field("pts", &self.pts())
+            .field("linesizes", &self.linesizes())
+            .finish()
+    }
+}
@@ -0,0 +1,48 @@
+use crate::{AVCodecID, AVPacket, AV_PKT_FLAG_KEY};
+use std::sync::Arc;
+
+/// 编码输出的数据包
+///
+/// 数据放在 `Arc<[u8]>` 中，克隆只增加引用计数，可以同时交给多个复用器或网络发送线程。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncodedPacket {
+    pub data: Arc<[u8]>,
+    /// 显示时间戳，单位为编码器时间基 (1/90000 秒)
+    pub pts: i64,
+    /// 解码时间戳，单位同 `pts`
+    pub dts: i64,
+    /// 持续时间，单位同 `pts`，编码器未给出时为 0
+    pub duration: i64,
+    pub keyframe: bool,
+    pub codec_id: AVCodecID,
+    /// 同一编码器输出的序号，从 0 开始连续递增
+    pub sequence: u64,
+}
+
+impl EncodedPacket {
+    /// 复制 `packet` 的数据，调用方负责之后 `av_packet_unref`
+    pub(crate) fn copy_from(packet: &AVPacket, codec_id: AVCodecID, sequence: u64) -> Self {
+        let data: Arc<[u8]> = if packet.data.is_null() || packet.size <= 0 {
+            Arc::from([])
+        } else {
+            Arc::from(unsafe { std::slice::from_raw_parts(packet.data, packet.size as usize) })
+        };
+        Self {
+            data,
+            pts: packet.pts,
+            dts: packet.dts,
+            duration: packet.duration,
+            keyframe: packet.flags & AV_PKT_FLAG_KEY as i32 != 0,
+            codec_id,
+            sequence,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+}
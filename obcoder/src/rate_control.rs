@@ -0,0 +1,342 @@
+use crate::{
+    av_opt_set, AVCodecContext, AV_CODEC_FLAG_QSCALE, AV_OPT_SEARCH_CHILDREN, FF_QP2LAMBDA,
+};
+use std::error::Error;
+use std::ffi::CString;
+
+/// 量化参数上限，覆盖 H.264/HEVC (0-51) 和 VP9/AV1 (0-63)
+const MAX_QP: u32 = 63;
+/// H.264/HEVC 编码器的量化参数上限
+const MAX_QP_H26X: u32 = 51;
+
+/// `codec_name` 编码器接受的量化参数上限
+fn max_qp(codec_name: &str) -> u32 {
+    match codec_name {
+        "libx264" | "libx265" => MAX_QP_H26X,
+        name if name.starts_with("h264_") || name.starts_with("hevc_") => MAX_QP_H26X,
+        _ => MAX_QP,
+    }
+}
+
+/// 码率控制模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateMode {
+    /// 恒定码率 (bit/s)，适合带宽受限的共享链路
+    Cbr { bitrate: i64 },
+    /// 平均码率 (bit/s)，可选峰值码率
+    Vbr {
+        bitrate: i64,
+        max_bitrate: Option<i64>,
+    },
+    /// 恒定质量，数值越小质量越高，适合录制归档
+    Crf(u32),
+    /// 恒定量化参数
+    Cqp(u32),
+}
+
+/// 码率控制参数
+///
+/// 统一描述后按编码器映射到 x264/x265、NVENC、QSV、VAAPI 以及 libvpx/libaom/SVT-AV1
+/// 各自的选项名，其他编码器只设置 `AVCodecContext` 的通用字段。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateControl {
+    pub mode: RateMode,
+    /// VBV 缓冲区大小 (bit)；CBR 和带峰值码率的 VBR 未指定时使用 1 秒的（峰值）码率
+    pub vbv_buffer_size: Option<i64>,
+    pub min_qp: Option<u32>,
+    pub max_qp: Option<u32>,
+}
+
+impl Default for RateControl {
+    fn default() -> Self {
+        Self::vbr(4_000_000, None)
+    }
+}
+
+impl RateControl {
+    pub fn cbr(bitrate: i64) -> Self {
+        Self::new(RateMode::Cbr { bitrate })
+    }
+
+    pub fn vbr(bitrate: i64, max_bitrate: Option<i64>) -> Self {
+        Self::new(RateMode::Vbr {
+            bitrate,
+            max_bitrate,
+        })
+    }
+
+    pub fn crf(quality: u32) -> Self {
+        Self::new(RateMode::Crf(quality))
+    }
+
+    pub fn cqp(qp: u32) -> Self {
+        Self::new(RateMode::Cqp(qp))
+    }
+
+    fn new(mode: RateMode) -> Self {
+        Self {
+            mode,
+            vbv_buffer_size: None,
+            min_qp: None,
+            max_qp: None,
+        }
+    }
+
+    pub fn with_vbv_buffer_size(mut self, bits: i64) -> Self {
+        self.vbv_buffer_size = Some(bits);
+        self
+    }
+
+    pub fn with_qp_range(mut self, min_qp: u32, max_qp: u32) -> Self {
+        self.min_qp = Some(min_qp);
+        self.max_qp = Some(max_qp);
+        self
+    }
+
+    /// 目标码率，恒定质量模式没有目标码率
+    pub fn bitrate(&self) -> Option<i64> {
+        match self.mode {
+            RateMode::Cbr { bitrate } | RateMode::Vbr { bitrate, .. } => Some(bitrate),
+            RateMode::Crf(_) | RateMode::Cqp(_) => None,
+        }
+    }
+
+    /// 与编码器无关的检查，量化参数按最宽的 0-63 检查
+    pub fn validate(&self) -> Result<(), Box<dyn Error>> {
+        self.validate_with(MAX_QP)
+    }
+
+    /// 按 `codec_name` 编码器实际的量化参数范围检查，例如 x264/NVENC H.264 只接受 0-51
+    pub(crate) fn validate_for(&self, codec_name: &str) -> Result<(), Box<dyn Error>> {
+        self.validate_with(max_qp(codec_name))
+            .map_err(|err| format!("{}: {}", codec_name, err).into())
+    }
+
+    fn validate_with(&self, max_qp: u32) -> Result<(), Box<dyn Error>> {
+        match self.mode {
+            RateMode::Cbr { bitrate } | RateMode::Vbr { bitrate, .. } if bitrate <= 0 => {
+                return Err(format!("invalid bitrate {}", bitrate).into());
+            }
+            RateMode::Vbr {
+                bitrate,
+                max_bitrate: Some(max),
+            } if max < bitrate => {
+                return Err(format!("max bitrate {} is below bitrate {}", max, bitrate).into());
+            }
+            RateMode::Crf(value) | RateMode::Cqp(value) if value > max_qp => {
+                return Err(format!("quality {} is out of range 0-{}", value, max_qp).into());
+            }
+            _ => {}
+        }
+        if self.vbv_buffer_size.is_some_and(|size| size <= 0) {
+            return Err("vbv buffer size must be positive".into());
+        }
+        if let Some(qp) = [self.min_qp, self.max_qp]
+            .into_iter()
+            .flatten()
+            .find(|&qp| qp > max_qp)
+        {
+            return Err(format!("qp {} is out of range 0-{}", qp, max_qp).into());
+        }
+        match (self.min_qp, self.max_qp) {
+            (Some(min), Some(max)) if min > max => {
+                Err(format!("min qp {} is above max qp {}", min, max).into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// 计算 `codec_name` 编码器需要的上下文字段和私有选项
+    pub(crate) fn settings(&self, codec_name: &str) -> RateSettings {
+        let family = EncoderFamily::of(codec_name);
+        let mut settings = RateSettings {
+            qmin: self.min_qp.map(|qp| qp as i32),
+            qmax: self.max_qp.map(|qp| qp as i32),
+            rc_buffer_size: self.vbv_buffer_size,
+            ..Default::default()
+        };
+
+        match self.mode {
+            RateMode::Cbr { bitrate } => {
+                settings.bit_rate = bitrate;
+                settings.rc_max_rate = bitrate;
+                settings.rc_min_rate = bitrate;
+                settings.rc_buffer_size = Some(self.vbv_buffer_size.unwrap_or(bitrate));
+                match family {
+                    EncoderFamily::X264 if codec_name == "libx264" => {
+                        settings.option("nal-hrd", "cbr")
+                    }
+                    EncoderFamily::Nvenc => settings.option("rc", "cbr"),
+                    EncoderFamily::Vaapi => settings.option("rc_mode", "CBR"),
+                    // QSV 在 bit_rate 等于 rc_max_rate 时自动选择 CBR
+                    _ => {}
+                }
+            }
+            RateMode::Vbr {
+                bitrate,
+                max_bitrate,
+            } => {
+                settings.bit_rate = bitrate;
+                settings.rc_max_rate = max_bitrate.unwrap_or(0);
+                // x264/x265 没有缓冲区大小时忽略峰值码率
+                if let Some(max) = max_bitrate {
+                    settings.rc_buffer_size = Some(self.vbv_buffer_size.unwrap_or(max));
+                }
+                match family {
+                    EncoderFamily::Nvenc => settings.option("rc", "vbr"),
+                    EncoderFamily::Vaapi => settings.option("rc_mode", "VBR"),
+                    _ => {}
+                }
+            }
+            RateMode::Crf(quality) => match family {
+                EncoderFamily::X264 | EncoderFamily::Libvpx => settings.option("crf", quality),
+                EncoderFamily::Nvenc => {
+                    settings.option("rc", "vbr");
+                    settings.option("cq", quality);
+                }
+                // 只设置 global_quality 时 QSV 使用 ICQ 模式
+                EncoderFamily::Qsv => settings.global_quality = Some(quality as i32),
+                EncoderFamily::Vaapi => {
+                    settings.option("rc_mode", "ICQ");
+                    settings.global_quality = Some(quality as i32);
+                }
+                EncoderFamily::Other => settings.global_quality = Some(quality as i32),
+            },
+            RateMode::Cqp(qp) => match family {
+                EncoderFamily::X264 => settings.option("qp", qp),
+                EncoderFamily::Nvenc => {
+                    settings.option("rc", "constqp");
+                    settings.option("qp", qp);
+                }
+                EncoderFamily::Qsv => {
+                    settings.qscale = true;
+                    settings.global_quality = Some((qp * FF_QP2LAMBDA) as i32);
+                }
+                EncoderFamily::Vaapi => {
+                    settings.option("rc_mode", "CQP");
+                    settings.option("qp", qp);
+                }
+                EncoderFamily::Libvpx | EncoderFamily::Other => {
+                    settings.qmin = Some(qp as i32);
+                    settings.qmax = Some(qp as i32);
+                }
+            },
+        }
+
+        // QSV 的 qmin/qmax 按帧类型分别设置
+        if family == EncoderFamily::Qsv {
+            for (name, qp) in [("min_qp", self.min_qp), ("max_qp", self.max_qp)] {
+                if let Some(qp) = qp {
+                    for frame_type in ["i", "p", "b"] {
+                        settings
+                            .options
+                            .push((format!("{}_{}", name, frame_type), qp.to_string()));
+                    }
+                }
+            }
+        }
+        settings
+    }
+
+    /// 写入尚未打开的编码上下文
+    pub(crate) fn apply(
+        &self,
+        ctx: *mut AVCodecContext,
+        codec_name: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        self.validate_for(codec_name)?;
+        let settings = self.settings(codec_name);
+        unsafe {
+            (*ctx).bit_rate = settings.bit_rate;
+            (*ctx).rc_max_rate = settings.rc_max_rate;
+            (*ctx).rc_min_rate = settings.rc_min_rate;
+            if let Some(size) = settings.rc_buffer_size {
+                (*ctx).rc_buffer_size = i32::try_from(size)?;
+            }
+            if let Some(qmin) = settings.qmin {
+                (*ctx).qmin = qmin;
+            }
+            if let Some(qmax) = settings.qmax {
+                (*ctx).qmax = qmax;
+            }
+            if let Some(quality) = settings.global_quality {
+                (*ctx).global_quality = quality;
+            }
+            if settings.qscale {
+                (*ctx).flags |= AV_CODEC_FLAG_QSCALE as i32;
+            }
+        }
+        for (key, value) in &settings.options {
+            let c_key = CString::new(key.as_str())?;
+            let c_value = CString::new(value.as_str())?;
+            let ret = unsafe {
+                av_opt_set(
+                    ctx.cast(),
+                    c_key.as_ptr(),
+                    c_value.as_ptr(),
+                    AV_OPT_SEARCH_CHILDREN as i32,
+                )
+            };
+            if ret < 0 {
+                return Err(format!("option {}={} is not supported", key, value).into());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 映射后的编码器设置
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct RateSettings {
+    pub bit_rate: i64,
+    pub rc_max_rate: i64,
+    pub rc_min_rate: i64,
+    pub rc_buffer_size: Option<i64>,
+    pub qmin: Option<i32>,
+    pub qmax: Option<i32>,
+    pub global_quality: Option<i32>,
+    pub qscale: bool,
+    /// 编码器私有选项
+    pub options: Vec<(String, String)>,
+}
+
+impl RateSettings {
+    fn option(&mut self, key: &str, value: impl ToString) {
+        self.options.push((key.to_string(), value.to_string()));
+    }
+
+    /// 查找私有选项的值
+    #[cfg(test)]
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// 选项名相同的一类编码器
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EncoderFamily {
+    /// libx264 / libx265
+    X264,
+    Nvenc,
+    Qsv,
+    Vaapi,
+    /// libvpx / libaom / SVT-AV1，均使用 `crf`
+    Libvpx,
+    Other,
+}
+
+impl EncoderFamily {
+    fn of(codec_name: &str) -> Self {
+        match codec_name {
+            "libx264" | "libx265" => EncoderFamily::X264,
+            "libvpx-vp9" | "libaom-av1" | "libsvtav1" => EncoderFamily::Libvpx,
+            name if name.ends_with("_nvenc") => EncoderFamily::Nvenc,
+            name if name.ends_with("_qsv") => EncoderFamily::Qsv,
+            name if name.ends_with("_vaapi") => EncoderFamily::Vaapi,
+            _ => EncoderFamily::Other,
+        }
+    }
+}
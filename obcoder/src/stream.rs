@@ -0,0 +1,123 @@
+use crate::bindings;
+use crate::error::check;
+use crate::frame::VideoFrame;
+use crate::{
+    av_frame_make_writable, calculate_frame_size, create_ob_stream, destroy_ob_stream,
+    ob_stream_get_frame, ob_stream_write_frame, AVPixelFormat,
+};
+use std::error::Error;
+use std::ptr::NonNull;
+
+/// 色彩转换流的所有权句柄
+///
+/// 持有 `create_ob_stream` 返回的指针，在 `Drop` 时调用 `destroy_ob_stream` 释放，
+/// 内部的 `SwsContext`、环形缓冲区和缓存帧不对外暴露。
+pub struct ObStream {
+    raw: NonNull<bindings::ObStream>,
+}
+
+// 转换流的所有资源都归该句柄独占，且不依赖创建线程，可以移动到其他线程使用。
+// 所有读写都需要 `&mut self`，因此不实现 `Sync`。
+unsafe impl Send for ObStream {}
+
+impl ObStream {
+    /// 创建 `width`x`height` 的转换流，把 `src_format` 的图像转换为 `dst_format`
+    pub fn new(
+        width: i32,
+        height: i32,
+        src_format: AVPixelFormat,
+        dst_format: AVPixelFormat,
+    ) -> Result<Self, Box<dyn Error>> {
+        if width <= 0 || height <= 0 {
+            return Err(format!("invalid stream size {}x{}", width, height).into());
+        }
+        let raw = unsafe { create_ob_stream(width, height, 5, src_format, dst_format) };
+        match NonNull::new(raw) {
+            Some(raw) => Ok(Self { raw }),
+            None => Err("can not create ob stream!".into()),
+        }
+    }
+
+    pub fn width(&self) -> i32 {
+        unsafe { self.raw.as_ref().width }
+    }
+
+    pub fn height(&self) -> i32 {
+        unsafe { self.raw.as_ref().height }
+    }
+
+    pub fn src_format(&self) -> AVPixelFormat {
+        unsafe { self.raw.as_ref().src_pixel }
+    }
+
+    pub fn dst_format(&self) -> AVPixelFormat {
+        unsafe { self.raw.as_ref().dst_pixel }
+    }
+
+    /// 每帧输入数据的字节数
+    pub fn input_frame_size(&self) -> usize {
+        unsafe { calculate_frame_size(self.width(), self.height(), self.src_format()) }
+    }
+
+    /// 写入一帧源格式的图像数据
+    pub fn write_frame(&mut self, image_data: &[u8]) -> Result<(), Box<dyn Error>> {
+        let expected = self.input_frame_size();
+        if image_data.len() < expected {
+            return Err(format!(
+                "frame data is {} bytes, expected {}",
+                image_data.len(),
+                expected
+            )
+            .into());
+        }
+        // C 接口只读取数据，参数声明为非 const 指针
+        let ret = unsafe {
+            ob_stream_write_frame(self.as_ptr(), image_data.as_ptr() as *mut u8, expected)
+        };
+        if ret == 0 {
+            Ok(())
+        } else {
+            Err("can not write frame data!".into())
+        }
+    }
+
+    /// 读取一帧转换后的目标格式图像
+    ///
+    /// 转换流内部缓存帧的缓冲区会被下一次转换覆盖，返回的帧复制为独立的缓冲区，
+    /// 可以在之后的读取中继续持有或移动到其他线程。
+    pub fn read_frame(&mut self) -> Result<VideoFrame, Box<dyn Error>> {
+        let frame = VideoFrame::empty()?;
+        let ret = unsafe { ob_stream_get_frame(self.as_ptr(), frame.as_ptr()) };
+        if ret != 0 {
+            return Err("can not get frame data!".into());
+        }
+        // 缓冲区同时被缓存帧引用，av_frame_make_writable 会复制出新的缓冲区
+        check(
+            unsafe { av_frame_make_writable(frame.as_ptr()) },
+            "av_frame_make_writable",
+        )?;
+        Ok(frame)
+    }
+
+    /// 原始指针，仅供 crate 内部与 C 接口交互
+    pub(crate) fn as_ptr(&self) -> *mut bindings::ObStream {
+        self.raw.as_ptr()
+    }
+}
+
+impl Drop for ObStream {
+    fn drop(&mut self) {
+        unsafe { destroy_ob_stream(self.raw.as_ptr()) }
+    }
+}
+
+impl std::fmt::Debug for ObStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ObStream")
+            .field("width", &self.width())
+            .field("height", &self.height())
+            .field("src_format", &self.src_format())
+            .field("dst_format", &self.dst_format())
+            .finish()
+    }
+}
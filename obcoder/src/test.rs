@@ -1,5 +1,5 @@
 use std::ffi::{CStr, CString};
-use crate::{avcodec_find_encoder_by_name, destroy_ob_stream, get_encoder, AVPixelFormat, AVPixelFormat_AV_PIX_FMT_BGRA, AVPixelFormat_AV_PIX_FMT_YUV420P, ObStream};
+use crate::{avcodec_find_encoder_by_name, get_encoder, AVPixelFormat, AVPixelFormat_AV_PIX_FMT_BGRA, AVPixelFormat_AV_PIX_FMT_YUV420P, AVPixelFormat_AV_PIX_FMT_NV12, AVCodecID_AV_CODEC_ID_H264, Decoder, EncodedPacket, Encoder, SelectionError, ObStream, RateControl, VideoCodec, VideoFrame};
 
 #[test]
 fn test() {
@@ -23,19 +23,398 @@ fn test_codec(){
 
 #[test]
 fn test_ob_stream_new(){
-    #[test]
-    fn test_ob_stream_new(){
-        let mut stream = ObStream::new(1920, 1080, AVPixelFormat_AV_PIX_FMT_BGRA, AVPixelFormat_AV_PIX_FMT_YUV420P);
-        println!("Created stream: {:?}", stream);
+    let mut stream = ObStream::new(1920, 1080, AVPixelFormat_AV_PIX_FMT_BGRA, AVPixelFormat_AV_PIX_FMT_YUV420P).unwrap();
+    println!("Created stream: {:?}", stream);
+    assert_eq!(stream.input_frame_size(), 1920 * 1080 * 4);
 
-        // 检查所有指针
-        println!("ctx: {:?}", stream.ctx);
-        println!("rb: {:?}", stream.rb);
-        println!("tmp_buffer: {:?}", stream.tmp_buffer);
-        println!("tmp_frame: {:?}", stream.tmp_frame);
-        println!("dst_frame: {:?}", stream.dst_frame);
+    let image = vec![0u8; stream.input_frame_size()];
+    stream.write_frame(&image).unwrap();
+    assert!(stream.write_frame(&image[1..]).is_err());
 
-        stream.destroy();
+    let frame = stream.read_frame().unwrap();
+    assert_eq!((frame.width(), frame.height()), (1920, 1080));
+    assert_eq!(frame.format(), AVPixelFormat_AV_PIX_FMT_YUV420P);
+    assert_eq!(frame.plane_count(), 3);
+    let linesizes = frame.linesizes();
+    assert_eq!(frame.plane(0).unwrap().len(), linesizes[0] as usize * 1080);
+    assert_eq!(frame.plane(1).unwrap().len(), linesizes[1] as usize * 540);
+    assert!(frame.plane(3).is_none());
+    // 离开作用域时自动释放
+}
+
+#[test]
+fn test_ob_stream_frame_outlives_next_read(){
+    let mut stream = ObStream::new(64, 36, AVPixelFormat_AV_PIX_FMT_BGRA, AVPixelFormat_AV_PIX_FMT_YUV420P).unwrap();
+    stream.write_frame(&vec![0u8; stream.input_frame_size()]).unwrap();
+    let first = stream.read_frame().unwrap();
+    let before = first.plane(0).unwrap().to_vec();
+
+    stream.write_frame(&vec![255u8; stream.input_frame_size()]).unwrap();
+    let second = stream.read_frame().unwrap();
+    // 第二次转换不能改写仍被持有的第一帧
+    assert_eq!(first.plane(0).unwrap(), &before[..]);
+    assert_ne!(second.plane(0), first.plane(0));
+}
+
+#[test]
+fn test_ob_stream_invalid_size(){
+    assert!(ObStream::new(0, 1080, AVPixelFormat_AV_PIX_FMT_BGRA, AVPixelFormat_AV_PIX_FMT_YUV420P).is_err());
+}
+
+#[test]
+fn test_video_frame_planes(){
+    let mut frame = VideoFrame::new(64, 36, AVPixelFormat_AV_PIX_FMT_NV12).unwrap();
+    assert_eq!(frame.plane_count(), 2);
+    let linesizes = frame.linesizes();
+    assert!(linesizes[0] >= 64 && linesizes[1] >= 64);
+    assert_eq!(frame.plane(1).unwrap().len(), linesizes[1] as usize * 18);
+
+    frame.plane_mut(0).unwrap().fill(16);
+    assert!(frame.plane(0).unwrap().iter().all(|&v| v == 16));
+    // NV12 只有两个平面
+    assert!(frame.plane(2).is_none());
+    assert!(frame.plane(4).is_none());
+    assert!(frame.plane_mut(2).is_err());
+    assert!(VideoFrame::new(64, 0, AVPixelFormat_AV_PIX_FMT_NV12).is_err());
+}
+
+#[test]
+fn test_encoder_encode(){
+    let mut stream = ObStream::new(1280, 720, AVPixelFormat_AV_PIX_FMT_BGRA, AVPixelFormat_AV_PIX_FMT_YUV420P).unwrap();
+    let mut encoder = Encoder::builder()
+        .size(1280, 720)
+        .fps(30)
+        .bitrate(2_000_000)
+        .gop(30)
+        .pixel_format(AVPixelFormat_AV_PIX_FMT_YUV420P)
+        .build()
+        .unwrap();
+    let mut packets = Vec::new();
+    for i in 0..10u8 {
+        let image = vec![i * 20; stream.input_frame_size()];
+        stream.write_frame(&image).unwrap();
+        let mut frame = stream.read_frame().unwrap();
+        packets.extend(encoder.encode(&mut frame).unwrap());
+    }
+    packets.extend(encoder.flush().unwrap());
+    assert_eq!(packets.len(), 10);
+    assert!(packets[0].keyframe);
+    assert!(packets.windows(2).all(|p| p[0].pts < p[1].pts));
+    assert!(packets.iter().enumerate().all(|(i, p)| p.sequence == i as u64));
+
+    let mut frame = pattern_frame(1280, 720, 0);
+    assert!(encoder.encode(&mut frame).is_err());
+}
+
+#[test]
+fn test_encoder_invalid_params(){
+    assert!(Encoder::builder().size(0, 720).build().is_err());
+    let err = Encoder::builder().size(1280, 720).codec("no_such_encoder").build().unwrap_err();
+    let err = err.downcast_ref::<SelectionError>().unwrap();
+    assert_eq!(err.rejected.len(), 1);
+    assert_eq!(err.rejected[0].codec, "no_such_encoder");
+
+    let mut encoder = Encoder::builder().size(1280, 720).build().unwrap();
+    let mut frame = pattern_frame(640, 360, 0);
+    assert!(encoder.encode(&mut frame).is_err());
+}
+
+#[test]
+fn test_encoded_packet_clone_and_send(){
+    let mut encoder = Encoder::builder().size(640, 360).build().unwrap();
+    let mut frame = pattern_frame(640, 360, 0);
+    let mut packets = encoder.encode(&mut frame).unwrap();
+    packets.extend(encoder.flush().unwrap());
+    let packet: EncodedPacket = packets.remove(0);
+    assert_eq!(packet.codec_id, AVCodecID_AV_CODEC_ID_H264);
+    assert!(packet.keyframe && !packet.is_empty());
+
+    // 克隆共享同一份数据
+    let copy = packet.clone();
+    assert!(std::sync::Arc::ptr_eq(&packet.data, &copy.data));
+    let len = std::thread::spawn(move || copy.len()).join().unwrap();
+    assert_eq!(len, packet.len());
+}
+
+#[test]
+fn test_encoder_software_fallback(){
+    // 没有 GPU 时硬件候选全部被跳过，回退到软件编码器
+    let encoder = Encoder::builder().size(640, 360).build().unwrap();
+    // 排在选中编码器之前的候选都应记录了原因
+    let candidates = crate::default_encoders(VideoCodec::H264);
+    let position = candidates.iter().position(|&c| c == encoder.codec_name()).unwrap();
+    assert_eq!(encoder.rejected().len(), position);
+    assert!(encoder.rejected().iter().all(|r| !r.reason.is_empty()));
+
+    let encoder = Encoder::builder().size(640, 360).codec("libx264").build().unwrap();
+    assert_eq!(encoder.codec_name(), "libx264");
+    assert!(encoder.rejected().is_empty());
+
+    let encoder = Encoder::builder().size(640, 360).preference(&["no_such_encoder", "libx264"]).build().unwrap();
+    assert_eq!(encoder.codec_name(), "libx264");
+    assert_eq!(encoder.rejected()[0].codec, "no_such_encoder");
+}
+
+#[test]
+fn test_probe_codecs(){
+    let encoders = crate::probe_encoders(AVCodecID_AV_CODEC_ID_H264, &["libx264"]);
+    assert!(encoders.iter().all(|info| info.codec_id == AVCodecID_AV_CODEC_ID_H264));
+    let first = &encoders[0];
+    assert_eq!(first.name, "libx264");
+    assert_eq!(first.kind, crate::CodecKind::Encoder);
+    assert!(!first.hardware);
+    assert!(first.pixel_formats.contains(&AVPixelFormat_AV_PIX_FMT_YUV420P));
+    assert!(first.profiles.iter().any(|p| p.name == "High"));
+    assert_eq!(first.max_resolution, None);
+    let mut first = first.clone();
+    crate::probe_max_resolution(&mut first);
+    assert!(first.max_resolution.unwrap().0 >= 1920);
+
+    let decoders = crate::probe_decoders(AVCodecID_AV_CODEC_ID_H264, &[] as &[&str]);
+    assert!(decoders.iter().any(|info| info.name == "h264" && info.max_resolution.is_none()));
+}
+
+/// 当前 FFmpeg 构建是否包含 `codec` 的软件编码器，没有时跳过该格式
+#[cfg(test)]
+fn has_software_encoder(codec: VideoCodec) -> bool {
+    crate::software_encoders(codec).iter().any(|name| {
+        let name = CString::new(*name).unwrap();
+        unsafe { !avcodec_find_encoder_by_name(name.as_ptr()).is_null() }
+    })
+}
+
+/// 按固定图案填充的 YUV420P 测试帧，`index` 让亮度图案逐帧平移，结果与分配时的内存内容无关
+#[cfg(test)]
+fn pattern_frame(width: i32, height: i32, index: usize) -> VideoFrame {
+    let mut frame = VideoFrame::new(width, height, AVPixelFormat_AV_PIX_FMT_YUV420P).unwrap();
+    let linesize = frame.linesizes()[0] as usize;
+    for (i, value) in frame.plane_mut(0).unwrap().iter_mut().enumerate() {
+        let (x, y) = (i % linesize, i / linesize);
+        *value = (x + y * 2 + index * 4) as u8;
+    }
+    frame.plane_mut(1).unwrap().fill(128);
+    frame.plane_mut(2).unwrap().fill(128);
+    frame
+}
+
+#[test]
+fn test_encoder_codecs(){
+    for codec in VideoCodec::ALL.into_iter().filter(|&codec| has_software_encoder(codec)) {
+        let mut encoder = Encoder::builder().video_codec(codec).size(640, 360).gop(10).build().unwrap();
+        assert_eq!(encoder.codec_id(), codec.id());
+
+        let mut packets = Vec::new();
+        for i in 0..5 {
+            let mut frame = pattern_frame(640, 360, i);
+            packets.extend(encoder.encode(&mut frame).unwrap());
+        }
+        packets.extend(encoder.flush().unwrap());
+        assert!(!packets.is_empty());
+        assert!(packets[0].keyframe);
+        assert!(packets.iter().all(|p| p.codec_id == codec.id()));
     }
 
-}
\ No newline at end of file
+    // 指定的编码器与编码格式不一致时拒绝
+    assert!(Encoder::builder().video_codec(VideoCodec::Av1).codec("libx264").size(640, 360).build().is_err());
+}
+
+#[test]
+fn test_encoder_stream_info(){
+    let encoder = Encoder::builder().codec("libx264").size(640, 360).global_header(true).build().unwrap();
+    let info = encoder.stream_info();
+    assert_eq!(info.mime_type(), "video/H264");
+    assert_eq!(info.bitstream_format(), crate::BitstreamFormat::AnnexB);
+    assert_eq!((info.width, info.height), (640, 360));
+    assert!(!info.extradata.is_empty());
+
+    if has_software_encoder(VideoCodec::Av1) {
+        let encoder = Encoder::builder().video_codec(VideoCodec::Av1).size(640, 360).global_header(true).build().unwrap();
+        assert_eq!(encoder.stream_info().bitstream_format(), crate::BitstreamFormat::Obu);
+    }
+}
+
+#[test]
+fn test_decoder_round_trip(){
+    let mut encoder = Encoder::builder().codec("libx264").size(640, 360).gop(10).build().unwrap();
+    let mut packets = Vec::new();
+    for i in 0..6 {
+        // 第 4 帧强制为关键帧，用于验证 reset 后的恢复
+        if i == 3 {
+            encoder.force_keyframe();
+        }
+        let mut frame = pattern_frame(640, 360, i);
+        packets.extend(encoder.encode(&mut frame).unwrap());
+    }
+    packets.extend(encoder.flush().unwrap());
+    assert_eq!(packets.len(), 6);
+    assert!(packets[0].keyframe && packets[3].keyframe);
+
+    let mut decoder = Decoder::new(VideoCodec::H264).unwrap();
+    assert_eq!(decoder.video_codec(), VideoCodec::H264);
+    let mut frames = Vec::new();
+    for packet in &packets {
+        frames.extend(decoder.decode(packet).unwrap());
+    }
+    frames.extend(decoder.flush().unwrap());
+    assert_eq!(frames.len(), 6);
+    assert!(frames.iter().all(|f| (f.width(), f.height()) == (640, 360)));
+
+    // 编码器不使用 B 帧，软件解码器每个数据包立即输出一帧
+    let mut decoder = Decoder::with_preference(VideoCodec::H264, &["h264"]).unwrap();
+    let mut decoded = 0;
+    for packet in &packets[..3] {
+        decoded += decoder.decode(packet).unwrap().len();
+    }
+    assert_eq!(decoded, 3);
+
+    // reset 丢弃缓存，之后 flush 没有输出
+    decoder.reset();
+    assert_eq!(decoder.flush().unwrap().len(), 0);
+
+    // 从新的关键帧开始继续送入，能完整恢复
+    frames.clear();
+    for packet in &packets[3..] {
+        frames.extend(decoder.decode(packet).unwrap());
+    }
+    frames.extend(decoder.flush().unwrap());
+    assert_eq!(frames.len(), 3);
+    assert!(frames.iter().all(|f| (f.width(), f.height()) == (640, 360)));
+
+    let mut hevc = Decoder::new(VideoCodec::Hevc).unwrap();
+    assert!(hevc.decode(&packets[0]).is_err());
+}
+
+#[test]
+fn test_decoder_global_header(){
+    let mut encoder = Encoder::builder().codec("libx264").size(320, 240).global_header(true).build().unwrap();
+    let mut frame = pattern_frame(320, 240, 0);
+    let mut packets = encoder.encode(&mut frame).unwrap();
+    packets.extend(encoder.flush().unwrap());
+
+    // 参数集只在 extradata 中，需要随流描述交给解码器
+    let mut decoder = Decoder::from_stream_info(&encoder.stream_info()).unwrap();
+    let mut frames = decoder.decode(&packets[0]).unwrap();
+    frames.extend(decoder.flush().unwrap());
+    assert_eq!(frames.len(), 1);
+}
+
+#[test]
+fn test_rate_control_mapping(){
+    let cbr = RateControl::cbr(3_000_000).with_qp_range(10, 40);
+    let x264 = cbr.settings("libx264");
+    assert_eq!((x264.bit_rate, x264.rc_max_rate, x264.rc_buffer_size), (3_000_000, 3_000_000, Some(3_000_000)));
+    assert_eq!(x264.get("nal-hrd"), Some("cbr"));
+    assert_eq!((x264.qmin, x264.qmax), (Some(10), Some(40)));
+    assert_eq!(cbr.settings("h264_nvenc").get("rc"), Some("cbr"));
+    assert_eq!(cbr.settings("hevc_vaapi").get("rc_mode"), Some("CBR"));
+    assert_eq!(cbr.settings("h264_qsv").get("max_qp_p"), Some("40"));
+
+    let crf = RateControl::crf(23);
+    assert_eq!(crf.settings("libx264").get("crf"), Some("23"));
+    assert_eq!(crf.settings("libsvtav1").get("crf"), Some("23"));
+    assert_eq!(crf.settings("h264_nvenc").get("cq"), Some("23"));
+    assert_eq!(crf.settings("h264_qsv").global_quality, Some(23));
+    assert_eq!(crf.settings("libx264").bit_rate, 0);
+
+    let cqp = RateControl::cqp(28);
+    assert_eq!(cqp.settings("h264_nvenc").get("rc"), Some("constqp"));
+    assert!(cqp.settings("h264_qsv").qscale);
+    assert_eq!(cqp.settings("h264_vaapi").get("qp"), Some("28"));
+
+    let vbr = RateControl::vbr(2_000_000, Some(4_000_000)).with_vbv_buffer_size(8_000_000);
+    assert_eq!(vbr.settings("libx264").rc_max_rate, 4_000_000);
+    assert_eq!(vbr.settings("libx264").rc_buffer_size, Some(8_000_000));
+    assert_eq!(RateControl::vbr(2_000_000, Some(4_000_000)).settings("libx264").rc_buffer_size, Some(4_000_000));
+    assert_eq!(RateControl::vbr(2_000_000, None).settings("libx264").rc_buffer_size, None);
+
+    assert!(RateControl::vbr(2_000_000, Some(1_000_000)).validate().is_err());
+    assert!(RateControl::crf(80).validate().is_err());
+    assert!(RateControl::cbr(1_000_000).with_qp_range(30, 20).validate().is_err());
+
+    // H.264/HEVC 编码器只接受 0-51，VP9/AV1 接受 0-63
+    let crf = RateControl::crf(55);
+    assert!(crf.validate().is_ok());
+    for name in ["libx264", "libx265", "h264_nvenc", "hevc_nvenc", "h264_qsv"] {
+        assert!(crf.validate_for(name).is_err(), "{}", name);
+    }
+    assert!(crf.validate_for("libsvtav1").is_ok());
+    let qp_range = RateControl::cbr(1_000_000).with_qp_range(10, 60);
+    assert!(qp_range.validate_for("h264_nvenc").is_err());
+    assert!(qp_range.validate_for("libvpx-vp9").is_ok());
+}
+
+#[test]
+fn test_encoder_rate_control(){
+    for rate_control in [RateControl::cbr(1_000_000), RateControl::crf(30), RateControl::cqp(30)] {
+        let mut encoder = Encoder::builder().codec("libx264").size(320, 240).rate_control(rate_control).build().unwrap();
+        let mut frame = pattern_frame(320, 240, 0);
+        let mut packets = encoder.encode(&mut frame).unwrap();
+        packets.extend(encoder.flush().unwrap());
+        assert_eq!(packets.len(), 1);
+    }
+    assert!(Encoder::builder().size(320, 240).rate_control(RateControl::cbr(0)).build().is_err());
+}
+
+#[test]
+fn test_encoder_runtime_reconfigure(){
+    let mut encoder = Encoder::builder().codec("libx264").size(320, 240).fps(30).gop(300).rate_control(RateControl::cbr(1_000_000)).build().unwrap();
+    let mut packets = Vec::new();
+    for i in 0..10 {
+        if i == 5 {
+            encoder.force_keyframe();
+            encoder.set_bitrate(500_000).unwrap();
+            encoder.set_fps(15).unwrap();
+        }
+        let mut frame = pattern_frame(320, 240, i);
+        packets.extend(encoder.encode(&mut frame).unwrap());
+    }
+    packets.extend(encoder.flush().unwrap());
+    packets.sort_by_key(|p| p.pts);
+    let pts: Vec<i64> = packets.iter().map(|p| p.pts).collect();
+    assert_eq!(pts, [0, 3000, 6000, 9000, 12000, 15000, 21000, 27000, 33000, 39000]);
+    let keyframes: Vec<i64> = packets.iter().filter(|p| p.keyframe).map(|p| p.pts).collect();
+    assert_eq!(keyframes, [0, 15000]);
+    assert_eq!(encoder.rate_control().bitrate(), Some(500_000));
+    // libx264 仍按打开时的 30fps 分配每帧码率，帧率减半后写入上下文的码率加倍
+    assert_eq!(encoder.context_bit_rate(), 1_000_000);
+
+    assert!(encoder.set_fps(0).is_err());
+    let mut encoder = Encoder::builder().codec("libx264").size(320, 240).rate_control(RateControl::crf(23)).build().unwrap();
+    assert!(encoder.set_bitrate(1_000_000).is_err());
+
+    // libvpx 不在编码过程中重新读取参数
+    if has_software_encoder(VideoCodec::Vp9) {
+        let mut encoder = Encoder::builder().video_codec(VideoCodec::Vp9).codec("libvpx-vp9").size(320, 240).build().unwrap();
+        assert!(encoder.set_bitrate(1_000_000).is_err());
+        assert!(encoder.set_fps(15).is_err());
+        assert_eq!(encoder.fps(), 30);
+    }
+}
+
+/// 每帧平均字节数按帧率换算为 bit/s
+#[cfg(test)]
+fn measured_bitrate(packets: &[EncodedPacket], fps: i64) -> i64 {
+    let bits: i64 = packets.iter().map(|p| p.len() as i64 * 8).sum();
+    bits * fps / packets.len() as i64
+}
+
+#[test]
+fn test_encoder_set_fps_keeps_bitrate(){
+    let mut encoder = Encoder::builder().codec("libx264").size(320, 240).fps(30).gop(300).rate_control(RateControl::cbr(1_000_000)).build().unwrap();
+    let mut packets = Vec::new();
+    for i in 0..200 {
+        if i == 100 {
+            encoder.set_fps(15).unwrap();
+        }
+        let mut frame = pattern_frame(320, 240, i);
+        packets.extend(encoder.encode(&mut frame).unwrap());
+    }
+    packets.extend(encoder.flush().unwrap());
+    assert_eq!(packets.len(), 200);
+
+    // 避开开头的关键帧，以及前瞻缓冲中跨越帧率变化的帧
+    let before = measured_bitrate(&packets[10..50], 30);
+    let after = measured_bitrate(&packets[150..], 15);
+    assert!((before as f64 / 1_000_000.0 - 1.0).abs() < 0.2, "{}", before);
+    assert!((after as f64 / 1_000_000.0 - 1.0).abs() < 0.2, "{}", after);
+}
@@ -0,0 +1,478 @@
+//! 共享屏幕上的标注图层。
+//!
+//! 标注以屏幕坐标保存为矢量图形，每帧合成时按帧尺寸缩放，
+//! 因此采集分辨率变化或缩放后标注仍落在正确位置。
+
+use crate::font;
+use crate::frame::Frame;
+use crate::overlay::{Canvas, Paint, Rect};
+use std::sync::{Arc, Mutex};
+
+/// 远程标注的自由笔迹最多点数
+pub const MAX_REMOTE_POINTS: usize = 4096;
+/// 远程标注的文字最大字符数
+pub const MAX_REMOTE_TEXT: usize = 256;
+/// 每个远程观众同时保留的标注数上限
+pub const MAX_REMOTE_ANNOTATIONS: usize = 64;
+/// 线宽上限（屏幕像素）
+pub const MAX_THICKNESS: f32 = 64.0;
+
+pub type AnnotationId = u64;
+
+/// 多线程共享的标注图层：采集线程合成，界面和网络线程添加标注
+pub type SharedAnnotationLayer = Arc<Mutex<AnnotationLayer>>;
+
+/// 标注的作者
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Author {
+    /// 共享屏幕的演示者
+    Presenter,
+    /// 远程观众，以其标识区分
+    Viewer(String),
+}
+
+/// 标注图形，坐标为屏幕像素
+#[derive(Debug, Clone, PartialEq)]
+pub enum Shape {
+    /// 自由笔迹
+    Freehand { points: Vec<(f32, f32)> },
+    /// 箭头，箭头位于 `to` 端
+    Arrow { from: (f32, f32), to: (f32, f32) },
+    /// 矩形框
+    Rectangle { rect: Rect },
+    /// 半透明高亮区域
+    Highlight { rect: Rect },
+    /// 文字，`position` 为左上角
+    Text {
+        position: (f32, f32),
+        text: String,
+        scale: usize,
+    },
+}
+
+/// 标注样式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Style {
+    pub paint: Paint,
+    /// 线宽（屏幕像素），高亮和文字忽略此项
+    pub thickness: f32,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self {
+            paint: Paint::solid([255, 48, 48]),
+            thickness: 4.0,
+        }
+    }
+}
+
+impl Style {
+    /// 高亮常用的半透明黄色
+    pub fn highlighter() -> Self {
+        Self {
+            paint: Paint::new([255, 230, 0], 0.35),
+            thickness: 0.0,
+        }
+    }
+}
+
+/// 一条标注
+#[derive(Debug, Clone, PartialEq)]
+pub struct Annotation {
+    pub id: AnnotationId,
+    pub author: Author,
+    pub shape: Shape,
+    pub style: Style,
+}
+
+/// 按添加顺序保存并合成标注
+#[derive(Debug, Clone)]
+pub struct AnnotationLayer {
+    screen_width: usize,
+    screen_height: usize,
+    annotations: Vec<Annotation>,
+    next_id: AnnotationId,
+}
+
+impl AnnotationLayer {
+    /// `screen_width`x`screen_height` 为标注坐标所在的屏幕尺寸
+    pub fn new(screen_width: usize, screen_height: usize) -> Self {
+        Self {
+            screen_width,
+            screen_height,
+            annotations: Vec::new(),
+            next_id: 1,
+        }
+    }
+
+    /// 包装为可跨线程共享的图层
+    pub fn shared(self) -> SharedAnnotationLayer {
+        Arc::new(Mutex::new(self))
+    }
+
+    pub fn screen_size(&self) -> (usize, usize) {
+        (self.screen_width, self.screen_height)
+    }
+
+    /// 屏幕尺寸变化时更新，已有标注的屏幕坐标保持不变
+    pub fn set_screen_size(&mut self, width: usize, height: usize) {
+        self.screen_width = width;
+        self.screen_height = height;
+    }
+
+    pub fn annotations(&self) -> &[Annotation] {
+        &self.annotations
+    }
+
+    pub fn len(&self) -> usize {
+        self.annotations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.annotations.is_empty()
+    }
+
+    /// 添加本地标注，不做限制
+    pub fn add(&mut self, author: Author, shape: Shape, style: Style) -> AnnotationId {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.annotations.push(Annotation {
+            id,
+            author,
+            shape,
+            style,
+        });
+        id
+    }
+
+    /// 添加远程观众提交的标注
+    ///
+    /// 检查点数、文字长度和每位观众的标注数量，坐标裁剪到屏幕范围内，
+    /// 坐标、透明度或线宽不是有限值时拒绝。
+    pub fn submit_remote(
+        &mut self,
+        viewer: &str,
+        shape: Shape,
+        style: Style,
+    ) -> Result<AnnotationId, AnnotationError> {
+        let author = Author::Viewer(viewer.to_string());
+        let count = self
+            .annotations
+            .iter()
+            .filter(|a| a.author == author)
+            .count();
+        if count >= MAX_REMOTE_ANNOTATIONS {
+            return Err(AnnotationError::QuotaExceeded(format!(
+                "viewer {} already has {} annotations",
+                viewer, count
+            )));
+        }
+
+        let shape = self.sanitize(shape)?;
+        if !style.paint.opacity.is_finite() || !style.thickness.is_finite() {
+            return Err(AnnotationError::InvalidShape(
+                "opacity and thickness must be finite".to_string(),
+            ));
+        }
+        let style = Style {
+            paint: Paint::new(style.paint.color, style.paint.opacity.clamp(0.0, 1.0)),
+            thickness: style.thickness.clamp(1.0, MAX_THICKNESS),
+        };
+        Ok(self.add(author, shape, style))
+    }
+
+    /// 撤销 `author` 最近添加的一条标注
+    pub fn undo(&mut self, author: &Author) -> Option<Annotation> {
+        let index = self.annotations.iter().rposition(|a| &a.author == author)?;
+        Some(self.annotations.remove(index))
+    }
+
+    pub fn remove(&mut self, id: AnnotationId) -> Option<Annotation> {
+        let index = self.annotations.iter().position(|a| a.id == id)?;
+        Some(self.annotations.remove(index))
+    }
+
+    /// 清除 `author` 的全部标注
+    pub fn clear_author(&mut self, author: &Author) {
+        self.annotations.retain(|a| &a.author != author);
+    }
+
+    pub fn clear(&mut self) {
+        self.annotations.clear();
+    }
+
+    /// 将所有标注合成到帧上，坐标按帧尺寸与屏幕尺寸之比缩放
+    pub fn composite(&self, frame: &mut Frame) {
+        if self.annotations.is_empty() || self.screen_width == 0 || self.screen_height == 0 {
+            return;
+        }
+        let sx = frame.width() as f32 / self.screen_width as f32;
+        let sy = frame.height() as f32 / self.screen_height as f32;
+        let map = |(x, y): (f32, f32)| (x * sx, y * sy);
+        let map_rect = |rect: &Rect| {
+            let (x, y) = map((rect.x as f32, rect.y as f32));
+            let (w, h) = map((rect.width as f32, rect.height as f32));
+            Rect::new(
+                x.round() as usize,
+                y.round() as usize,
+                w.round() as usize,
+                h.round() as usize,
+            )
+        };
+        let stroke_scale = (sx + sy) / 2.0;
+
+        let mut canvas = Canvas::new(frame);
+        for annotation in &self.annotations {
+            let paint = annotation.style.paint;
+            let thickness = annotation.style.thickness * stroke_scale;
+            match &annotation.shape {
+                Shape::Freehand { points } => {
+                    let points: Vec<(f32, f32)> = points.iter().copied().map(map).collect();
+                    canvas.polyline(&points, thickness, paint);
+                }
+                Shape::Arrow { from, to } => {
+                    let (from, to) = (map(*from), map(*to));
+                    canvas.polyline(&arrow_points(from, to, thickness), thickness, paint);
+                }
+                Shape::Rectangle { rect } => {
+                    canvas.stroke_rect(map_rect(rect), thickness.round().max(1.0) as usize, paint)
+                }
+                Shape::Highlight { rect } => canvas.fill_rect(map_rect(rect), paint),
+                Shape::Text {
+                    position,
+                    text,
+                    scale,
+                } => {
+                    let (x, y) = map(*position);
+                    let scale = ((*scale as f32 * stroke_scale).round() as usize).max(1);
+                    canvas.text(x.max(0.0) as usize, y.max(0.0) as usize, text, scale, paint);
+                }
+            }
+        }
+    }
+
+    /// 检查远程标注并把坐标裁剪到屏幕内
+    fn sanitize(&self, shape: Shape) -> Result<Shape, AnnotationError> {
+        let (w, h) = (self.screen_width as f32, self.screen_height as f32);
+        let clamp = |(x, y): (f32, f32)| {
+            if x.is_finite() && y.is_finite() {
+                Ok((x.clamp(0.0, w), y.clamp(0.0, h)))
+            } else {
+                Err(AnnotationError::InvalidShape(
+                    "coordinates must be finite".to_string(),
+                ))
+            }
+        };
+        let clamp_rect = |rect: Rect| {
+            let x = rect.x.min(self.screen_width);
+            let y = rect.y.min(self.screen_height);
+            Rect::new(
+                x,
+                y,
+                rect.width.min(self.screen_width - x),
+                rect.height.min(self.screen_height - y),
+            )
+        };
+
+        Ok(match shape {
+            Shape::Freehand { points } => {
+                if points.is_empty() || points.len() > MAX_REMOTE_POINTS {
+                    return Err(AnnotationError::InvalidShape(format!(
+                        "freehand stroke must have 1 to {} points, got {}",
+                        MAX_REMOTE_POINTS,
+                        points.len()
+                    )));
+                }
+                Shape::Freehand {
+                    points: points.into_iter().map(clamp).collect::<Result<_, _>>()?,
+                }
+            }
+            Shape::Arrow { from, to } => Shape::Arrow {
+                from: clamp(from)?,
+                to: clamp(to)?,
+            },
+            Shape::Rectangle { rect } => Shape::Rectangle {
+                rect: clamp_rect(rect),
+            },
+            Shape::Highlight { rect } => Shape::Highlight {
+                rect: clamp_rect(rect),
+            },
+            Shape::Text {
+                position,
+                text,
+                scale,
+            } => {
+                let length = text.chars().count();
+                if length > MAX_REMOTE_TEXT {
+                    return Err(AnnotationError::InvalidShape(format!(
+                        "text is {} characters, limit is {}",
+                        length, MAX_REMOTE_TEXT
+                    )));
+                }
+                // 限制放大倍数，避免单条文字铺满屏幕
+                let max_scale = (self.screen_height / font::GLYPH_HEIGHT / 4).max(1);
+                Shape::Text {
+                    position: clamp(position)?,
+                    text,
+                    scale: scale.clamp(1, max_scale),
+                }
+            }
+        })
+    }
+}
+
+/// 箭头的折线：杆身加两侧箭头，箭头张角 ±25°
+fn arrow_points(from: (f32, f32), to: (f32, f32), thickness: f32) -> Vec<(f32, f32)> {
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    let length = (dx * dx + dy * dy).sqrt();
+    if length == 0.0 {
+        return vec![from];
+    }
+    let head = (thickness * 4.0).max(10.0).min(length * 0.5);
+    let angle = dy.atan2(dx);
+    let wing = |offset: f32| {
+        let a = angle + std::f32::consts::PI + offset;
+        (to.0 + head * a.cos(), to.1 + head * a.sin())
+    };
+    let spread = 25f32.to_radians();
+    vec![from, to, wing(spread), to, wing(-spread)]
+}
+
+/// 标注操作错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnnotationError {
+    InvalidShape(String),
+    QuotaExceeded(String),
+}
+
+impl std::fmt::Display for AnnotationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AnnotationError::InvalidShape(msg) => write!(f, "Invalid annotation: {}", msg),
+            AnnotationError::QuotaExceeded(msg) => write!(f, "Annotation quota exceeded: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for AnnotationError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::PixelFormat;
+
+    #[test]
+    fn test_add_undo_clear() {
+        let mut layer = AnnotationLayer::new(100, 100);
+        let rect = Shape::Rectangle {
+            rect: Rect::new(10, 10, 20, 20),
+        };
+        let first = layer.add(Author::Presenter, rect.clone(), Style::default());
+        let remote = layer
+            .submit_remote("alice", rect.clone(), Style::default())
+            .unwrap();
+        let second = layer.add(Author::Presenter, rect, Style::default());
+        assert_eq!(layer.len(), 3);
+
+        // 演示者撤销不会影响观众的标注
+        assert_eq!(layer.undo(&Author::Presenter).unwrap().id, second);
+        assert_eq!(layer.undo(&Author::Presenter).unwrap().id, first);
+        assert!(layer.undo(&Author::Presenter).is_none());
+        assert_eq!(layer.annotations()[0].id, remote);
+
+        layer.clear_author(&Author::Viewer("alice".to_string()));
+        assert!(layer.is_empty());
+    }
+
+    #[test]
+    fn test_remote_validation() {
+        let mut layer = AnnotationLayer::new(100, 50);
+        let too_long = Shape::Freehand {
+            points: vec![(0.0, 0.0); MAX_REMOTE_POINTS + 1],
+        };
+        assert!(matches!(
+            layer.submit_remote("bob", too_long, Style::default()),
+            Err(AnnotationError::InvalidShape(_))
+        ));
+
+        let arrow = Shape::Arrow {
+            from: (1.0, 1.0),
+            to: (2.0, 2.0),
+        };
+        let mut style = Style::default();
+        style.paint.opacity = f32::NAN;
+        assert!(matches!(
+            layer.submit_remote("bob", arrow.clone(), style),
+            Err(AnnotationError::InvalidShape(_))
+        ));
+        let style = Style {
+            thickness: f32::INFINITY,
+            ..Style::default()
+        };
+        assert!(matches!(
+            layer.submit_remote("bob", arrow, style),
+            Err(AnnotationError::InvalidShape(_))
+        ));
+        assert!(layer.annotations().is_empty());
+
+        let outside = Shape::Arrow {
+            from: (-20.0, 10.0),
+            to: (500.0, 80.0),
+        };
+        let id = layer
+            .submit_remote("bob", outside, Style::default())
+            .unwrap();
+        assert_eq!(
+            layer.annotations()[0].shape,
+            Shape::Arrow {
+                from: (0.0, 10.0),
+                to: (100.0, 50.0)
+            }
+        );
+        assert_eq!(
+            layer.remove(id).unwrap().author,
+            Author::Viewer("bob".to_string())
+        );
+
+        for _ in 0..MAX_REMOTE_ANNOTATIONS {
+            let highlight = Shape::Highlight {
+                rect: Rect::new(0, 0, 10, 10),
+            };
+            layer
+                .submit_remote("bob", highlight, Style::highlighter())
+                .unwrap();
+        }
+        let highlight = Shape::Highlight {
+            rect: Rect::new(0, 0, 10, 10),
+        };
+        assert!(matches!(
+            layer.submit_remote("bob", highlight, Style::highlighter()),
+            Err(AnnotationError::QuotaExceeded(_))
+        ));
+    }
+
+    #[test]
+    fn test_composite_scales_to_frame() {
+        let mut layer = AnnotationLayer::new(200, 200);
+        let style = Style {
+            paint: Paint::solid([255, 255, 255]),
+            thickness: 2.0,
+        };
+        layer.add(
+            Author::Presenter,
+            Shape::Highlight {
+                rect: Rect::new(100, 100, 100, 100),
+            },
+            style,
+        );
+
+        // 帧是屏幕的一半大小，高亮落在右下四分之一
+        let mut frame = Frame::black(PixelFormat::Nv12, 100, 100);
+        layer.composite(&mut frame);
+        let luma = frame.planes()[0];
+        assert_eq!(luma[49 * 100 + 49], 16);
+        assert_eq!(luma[50 * 100 + 50], 235);
+        assert_eq!(luma[99 * 100 + 99], 235);
+    }
+}
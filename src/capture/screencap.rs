@@ -1,3 +1,4 @@
+use crate::frame::PixelFormat;
 use crate::nv12::NV12Organizer;
 use crate::screen::get_screen_size;
 use scap::capturer::{Capturer, Options};
@@ -127,6 +128,45 @@ impl ScreenCapture {
         Ok(image)
     }
 
+    /// 连续采集 `count` 帧并复制为紧密排列的 [`crate::frame::Frame`]，
+    /// 可直接交给 [`crate::clip::export_gif`] / [`crate::clip::export_apng`] 导出
+    pub(crate) fn capture_clip(
+        &mut self,
+        count: usize,
+    ) -> Result<Vec<crate::frame::Frame>, Box<dyn std::error::Error>> {
+        let mut frames = Vec::with_capacity(count);
+        while frames.len() < count {
+            let clip_frame = match self.capture.get_next_frame()? {
+                Frame::BGRA(frame) => {
+                    if frame.data.is_empty() {
+                        continue;
+                    }
+                    crate::frame::Frame::new(
+                        PixelFormat::Bgra,
+                        frame.width as usize,
+                        frame.height as usize,
+                        frame.data,
+                    )?
+                }
+                Frame::YUVFrame(frame) => {
+                    let (width, height) = (frame.width as usize, frame.height as usize);
+                    let data = NV12Organizer::organize_nv12_data(
+                        &frame.luminance_bytes,
+                        frame.luminance_stride as usize,
+                        &frame.chrominance_bytes,
+                        frame.chrominance_stride as usize,
+                        width,
+                        height,
+                    )?;
+                    crate::frame::Frame::new(PixelFormat::Nv12, width, height, data)?
+                }
+                _ => return Err(Box::from("can not match frame type!")),
+            };
+            frames.push(clip_frame);
+        }
+        Ok(frames)
+    }
+
     pub(crate) fn close(&mut self) {
         self.capture.stop_capture();
     }
@@ -0,0 +1,431 @@
+//! 把一段采集帧导出为 GIF / APNG 动图。
+//!
+//! 帧序列通常来自 `ScreenCapture::capture_clip`，按采集帧率传入 [`export_gif`] 或 [`export_apng`]。
+
+use crate::convert::{to_rgba, ColorMatrix};
+use crate::frame::Frame;
+use crate::nv12::NV12Error;
+use crate::scale::{fit_rect, scale_frame, ScaleFilter};
+use std::borrow::Cow;
+use std::io::Write;
+
+/// 调色板量化时最多采样的像素数
+const PALETTE_SAMPLES: usize = 1 << 18;
+
+/// 动图导出参数
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClipOptions {
+    /// 输出帧率，低于源帧率时按时间抽帧
+    pub fps: f32,
+    /// 输出尺寸上限，按宽高比缩小，不会放大
+    pub max_size: Option<(usize, usize)>,
+    pub filter: ScaleFilter,
+    /// GIF 调色板颜色数 (2-256)
+    pub colors: usize,
+    /// GIF 是否使用 Floyd-Steinberg 误差扩散抖动
+    pub dither: bool,
+    /// 播放次数，0 表示无限循环
+    pub loop_count: u16,
+}
+
+impl Default for ClipOptions {
+    fn default() -> Self {
+        Self {
+            fps: 10.0,
+            max_size: Some((960, 540)),
+            filter: ScaleFilter::Area,
+            colors: 256,
+            dither: true,
+            loop_count: 0,
+        }
+    }
+}
+
+/// 源帧率必须为正数
+fn check_fps(source_fps: f32) -> Result<(), ClipError> {
+    if source_fps.is_finite() && source_fps > 0.0 {
+        Ok(())
+    } else {
+        Err(ClipError::InvalidFps(source_fps))
+    }
+}
+
+/// 按输出帧率从 `count` 个源帧中选出的帧序号
+///
+/// `target_fps` 不大于 0 或不低于源帧率时保留全部帧；源帧率不是正数时返回错误。
+pub fn select_frames(
+    count: usize,
+    source_fps: f32,
+    target_fps: f32,
+) -> Result<Vec<usize>, ClipError> {
+    check_fps(source_fps)?;
+    if count == 0 {
+        return Ok(Vec::new());
+    }
+    if target_fps.is_nan() || target_fps <= 0.0 || target_fps >= source_fps {
+        return Ok((0..count).collect());
+    }
+
+    let duration = count as f64 / source_fps as f64;
+    let outputs = (duration * target_fps as f64).ceil() as usize;
+    Ok((0..outputs)
+        .map(|k| ((k as f64 / target_fps as f64 * source_fps as f64) as usize).min(count - 1))
+        .collect())
+}
+
+/// 抽帧、缩放并转换为 RGBA，返回 (宽, 高, 帧列表)
+fn prepare(
+    frames: &[Frame],
+    source_fps: f32,
+    options: &ClipOptions,
+) -> Result<(usize, usize, Vec<Vec<u8>>), ClipError> {
+    let first = frames.first().ok_or(ClipError::Empty)?;
+    let (width, height) = match options.max_size {
+        Some((max_w, max_h)) if first.width() > max_w || first.height() > max_h => {
+            let (w, h, _, _) =
+                fit_rect(first.format(), first.width(), first.height(), max_w, max_h)?;
+            (w, h)
+        }
+        _ => (first.width(), first.height()),
+    };
+    if width > u16::MAX as usize || height > u16::MAX as usize {
+        return Err(
+            NV12Error::InvalidPlanes(format!("clip size {}x{} too large", width, height)).into(),
+        );
+    }
+
+    select_frames(frames.len(), source_fps, options.fps)?
+        .into_iter()
+        .map(|i| {
+            let frame = &frames[i];
+            let scaled = if frame.width() == width && frame.height() == height {
+                Cow::Borrowed(frame)
+            } else {
+                Cow::Owned(scale_frame(frame, width, height, options.filter)?)
+            };
+            Ok(to_rgba(&scaled, ColorMatrix::default()))
+        })
+        .collect::<Result<Vec<_>, ClipError>>()
+        .map(|rgba| (width, height, rgba))
+}
+
+/// 单帧显示时长，单位为 1/100 秒
+fn frame_delay_cs(options: &ClipOptions, source_fps: f32) -> Result<u16, ClipError> {
+    check_fps(source_fps)?;
+    let fps = if options.fps > 0.0 && options.fps < source_fps {
+        options.fps
+    } else {
+        source_fps
+    };
+    Ok(((100.0 / fps).round() as u16).max(2))
+}
+
+/// 导出为 GIF，所有帧共用一个中位切分调色板
+pub fn export_gif<W: Write>(
+    frames: &[Frame],
+    source_fps: f32,
+    options: &ClipOptions,
+    writer: W,
+) -> Result<(), ClipError> {
+    let (width, height, rgba_frames) = prepare(frames, source_fps, options)?;
+    let palette = Palette::median_cut(&rgba_frames, options.colors.clamp(2, 256));
+
+    let mut encoder = gif::Encoder::new(writer, width as u16, height as u16, &palette.to_bytes())?;
+    encoder.set_repeat(match options.loop_count {
+        0 => gif::Repeat::Infinite,
+        n => gif::Repeat::Finite(n - 1),
+    })?;
+
+    let delay = frame_delay_cs(options, source_fps)?;
+    for rgba in &rgba_frames {
+        let indices = palette.map_frame(rgba, width, height, options.dither);
+        let frame = gif::Frame {
+            width: width as u16,
+            height: height as u16,
+            delay,
+            buffer: Cow::Owned(indices),
+            ..gif::Frame::default()
+        };
+        encoder.write_frame(&frame)?;
+    }
+    Ok(())
+}
+
+/// 导出为 APNG，保留全部颜色
+pub fn export_apng<W: Write>(
+    frames: &[Frame],
+    source_fps: f32,
+    options: &ClipOptions,
+    writer: W,
+) -> Result<(), ClipError> {
+    let (width, height, rgba_frames) = prepare(frames, source_fps, options)?;
+
+    let mut encoder = png::Encoder::new(writer, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_animated(rgba_frames.len() as u32, options.loop_count as u32)?;
+    encoder.set_frame_delay(frame_delay_cs(options, source_fps)?, 100)?;
+
+    let mut png_writer = encoder.write_header()?;
+    for rgba in &rgba_frames {
+        png_writer.write_image_data(rgba)?;
+    }
+    png_writer.finish()?;
+    Ok(())
+}
+
+/// GIF 调色板
+struct Palette {
+    colors: Vec<[u8; 3]>,
+}
+
+impl Palette {
+    /// 中位切分：反复沿范围最大的通道在中位数处切分颜色盒
+    fn median_cut(frames: &[Vec<u8>], max_colors: usize) -> Self {
+        let total: usize = frames.iter().map(|f| f.len() / 4).sum();
+        let step = total.div_ceil(PALETTE_SAMPLES).max(1);
+        let mut samples: Vec<[u8; 3]> = frames
+            .iter()
+            .flat_map(|f| f.chunks_exact(4).step_by(step))
+            .map(|p| [p[0], p[1], p[2]])
+            .collect();
+
+        let mut boxes: Vec<&mut [[u8; 3]]> = vec![&mut samples[..]];
+        while boxes.len() < max_colors {
+            // 选出范围最大且可切分的盒
+            let candidate = boxes
+                .iter()
+                .enumerate()
+                .filter(|(_, b)| b.len() > 1)
+                .map(|(i, b)| {
+                    let (channel, range) = widest_channel(b);
+                    (i, channel, range)
+                })
+                .filter(|&(_, _, range)| range > 0)
+                .max_by_key(|&(_, _, range)| range);
+            let Some((index, channel, _)) = candidate else {
+                break;
+            };
+
+            let b = boxes.swap_remove(index);
+            b.sort_unstable_by_key(|c| c[channel]);
+            let (low, high) = b.split_at_mut(b.len() / 2);
+            boxes.push(low);
+            boxes.push(high);
+        }
+
+        let colors = boxes
+            .iter()
+            .filter(|b| !b.is_empty())
+            .map(|b| {
+                let mut sum = [0u64; 3];
+                for c in b.iter() {
+                    for (s, &v) in sum.iter_mut().zip(c) {
+                        *s += v as u64;
+                    }
+                }
+                sum.map(|s| (s / b.len() as u64) as u8)
+            })
+            .collect();
+        Self { colors }
+    }
+
+    /// 调色板字节，补齐到 2 的幂
+    fn to_bytes(&self) -> Vec<u8> {
+        let size = self.colors.len().next_power_of_two().max(2);
+        let mut bytes: Vec<u8> = self.colors.iter().flatten().copied().collect();
+        bytes.resize(size * 3, 0);
+        bytes
+    }
+
+    fn nearest(&self, rgb: [i32; 3]) -> u8 {
+        let mut best = 0;
+        let mut best_dist = i32::MAX;
+        for (i, c) in self.colors.iter().enumerate() {
+            let d: i32 = (0..3).map(|k| (rgb[k] - c[k] as i32).pow(2)).sum();
+            if d < best_dist {
+                best = i;
+                best_dist = d;
+            }
+        }
+        best as u8
+    }
+
+    /// 将 RGBA 帧映射为调色板索引，使用 RGB555 查找表缓存最近颜色
+    fn map_frame(&self, rgba: &[u8], width: usize, height: usize, dither: bool) -> Vec<u8> {
+        let mut lut = vec![u16::MAX; 1 << 15];
+        let mut lookup = |rgb: [i32; 3]| -> u8 {
+            let key = ((rgb[0] as usize >> 3) << 10)
+                | ((rgb[1] as usize >> 3) << 5)
+                | (rgb[2] as usize >> 3);
+            if lut[key] == u16::MAX {
+                lut[key] = self.nearest(rgb) as u16;
+            }
+            lut[key] as u8
+        };
+
+        let mut indices = vec![0u8; width * height];
+        if !dither {
+            for (i, p) in rgba.chunks_exact(4).enumerate() {
+                indices[i] = lookup([p[0] as i32, p[1] as i32, p[2] as i32]);
+            }
+            return indices;
+        }
+
+        // Floyd-Steinberg：当前行与下一行的累计误差
+        let mut errors = vec![[0i32; 3]; width * 2];
+        for y in 0..height {
+            let (current, next) = errors.split_at_mut(width);
+            for x in 0..width {
+                let i = (y * width + x) * 4;
+                let rgb: [i32; 3] = std::array::from_fn(|k| {
+                    (rgba[i + k] as i32 + current[x][k] / 16).clamp(0, 255)
+                });
+                let index = lookup(rgb);
+                indices[y * width + x] = index;
+
+                let chosen = self.colors[index as usize];
+                for k in 0..3 {
+                    let err = rgb[k] - chosen[k] as i32;
+                    if x + 1 < width {
+                        current[x + 1][k] += err * 7;
+                        next[x + 1][k] += err;
+                    }
+                    if x > 0 {
+                        next[x - 1][k] += err * 3;
+                    }
+                    next[x][k] += err * 5;
+                }
+            }
+            current.copy_from_slice(next);
+            next.fill([0; 3]);
+        }
+        indices
+    }
+}
+
+/// 范围最大的通道及其范围
+fn widest_channel(colors: &[[u8; 3]]) -> (usize, u8) {
+    (0..3)
+        .map(|k| {
+            let (min, max) = colors
+                .iter()
+                .fold((u8::MAX, 0u8), |(lo, hi), c| (lo.min(c[k]), hi.max(c[k])));
+            (k, max.saturating_sub(min))
+        })
+        .max_by_key(|&(_, range)| range)
+        .unwrap_or((0, 0))
+}
+
+/// 动图导出错误
+#[derive(Debug)]
+pub enum ClipError {
+    /// 没有可导出的帧
+    Empty,
+    /// 源帧率不是正数
+    InvalidFps(f32),
+    Frame(NV12Error),
+    Gif(gif::EncodingError),
+    Png(png::EncodingError),
+}
+
+impl std::fmt::Display for ClipError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClipError::Empty => write!(f, "No frames to export"),
+            ClipError::InvalidFps(fps) => write!(f, "Invalid source frame rate: {}", fps),
+            ClipError::Frame(err) => write!(f, "Invalid frame: {}", err),
+            ClipError::Gif(err) => write!(f, "GIF encoding failed: {}", err),
+            ClipError::Png(err) => write!(f, "APNG encoding failed: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ClipError {}
+
+impl From<NV12Error> for ClipError {
+    fn from(err: NV12Error) -> Self {
+        ClipError::Frame(err)
+    }
+}
+
+impl From<gif::EncodingError> for ClipError {
+    fn from(err: gif::EncodingError) -> Self {
+        ClipError::Gif(err)
+    }
+}
+
+impl From<png::EncodingError> for ClipError {
+    fn from(err: png::EncodingError) -> Self {
+        ClipError::Png(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::PixelFormat;
+
+    fn solid(value: u8) -> Frame {
+        let data = [value, 255 - value, value / 2, 255].repeat(8 * 4);
+        Frame::new(PixelFormat::Bgra, 8, 4, data).unwrap()
+    }
+
+    #[test]
+    fn test_select_frames() {
+        assert_eq!(select_frames(6, 30.0, 10.0).unwrap(), vec![0, 3]);
+        assert_eq!(select_frames(3, 10.0, 30.0).unwrap(), vec![0, 1, 2]);
+        assert!(select_frames(0, 30.0, 10.0).unwrap().is_empty());
+        for fps in [0.0, -30.0, f32::NAN, f32::INFINITY] {
+            assert!(matches!(
+                select_frames(6, fps, 10.0),
+                Err(ClipError::InvalidFps(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_export_gif() {
+        let frames: Vec<Frame> = (0..4).map(|i| solid(i * 60)).collect();
+        let options = ClipOptions {
+            fps: 2.0,
+            max_size: Some((4, 4)),
+            ..ClipOptions::default()
+        };
+
+        let mut bytes = Vec::new();
+        export_gif(&frames, 4.0, &options, &mut bytes).unwrap();
+        assert!(bytes.starts_with(b"GIF89a"));
+
+        let mut decoder = gif::DecodeOptions::new().read_info(&bytes[..]).unwrap();
+        assert_eq!((decoder.width(), decoder.height()), (4, 2));
+        let mut count = 0;
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            assert_eq!(frame.delay, 50);
+            count += 1;
+        }
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn test_export_apng() {
+        let frames: Vec<Frame> = (0..3).map(|i| solid(i * 80)).collect();
+        let mut bytes = Vec::new();
+        export_apng(&frames, 10.0, &ClipOptions::default(), &mut bytes).unwrap();
+
+        let decoder = png::Decoder::new(&bytes[..]);
+        let reader = decoder.read_info().unwrap();
+        let control = reader.info().animation_control().unwrap();
+        assert_eq!(control.num_frames, 3);
+        assert_eq!(control.num_plays, 0);
+
+        assert!(matches!(
+            export_apng(&[], 10.0, &ClipOptions::default(), Vec::new()),
+            Err(ClipError::Empty)
+        ));
+        assert!(matches!(
+            export_gif(&frames, 0.0, &ClipOptions::default(), Vec::new()),
+            Err(ClipError::InvalidFps(_))
+        ));
+    }
+}
@@ -0,0 +1,363 @@
+use crate::convert::{convert, ColorMatrix};
+use crate::font;
+use crate::frame::{Frame, PixelFormat};
+use crate::nv12::NV12Error;
+use crate::overlay::{Canvas, Paint, Rect};
+use crate::scale::{blit, scale_to_fit, ScaleFilter};
+use crate::scene::{SceneDetector, SceneDetectorOptions};
+use crate::y4m::{Y4mError, Y4mReader};
+use std::io::BufRead;
+use std::time::Duration;
+
+/// 缩略图的选取方式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Selection {
+    /// 在整段录像中均匀取帧
+    Even,
+    /// 取画面变化最大的帧，`threshold` 为相邻帧平均亮度差 (0.0-1.0) 的下限
+    ///
+    /// 第一帧总会被选中；超过阈值的帧不足 `count` 时，缩略图数量相应减少。
+    SceneChange { threshold: f32 },
+}
+
+/// 缩略图拼版参数
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ContactSheetOptions {
+    /// 缩略图数量上限
+    pub count: usize,
+    /// 每行缩略图数
+    pub columns: usize,
+    /// 单个缩略图尺寸，按宽高比缩放，空白处为黑边
+    pub thumb_size: (usize, usize),
+    /// 缩略图之间以及四周的间距
+    pub spacing: usize,
+    /// 背景色 (R, G, B)
+    pub background: [u8; 3],
+    pub selection: Selection,
+    pub filter: ScaleFilter,
+    /// 是否在缩略图左下角绘制时间戳
+    pub timestamps: bool,
+    /// 时间戳字体放大倍数
+    pub label_scale: usize,
+}
+
+impl Default for ContactSheetOptions {
+    fn default() -> Self {
+        Self {
+            count: 16,
+            columns: 4,
+            thumb_size: (320, 180),
+            spacing: 8,
+            background: [32, 32, 32],
+            selection: Selection::Even,
+            filter: ScaleFilter::Area,
+            timestamps: true,
+            label_scale: 1,
+        }
+    }
+}
+
+/// 已选中的缩略图
+struct Thumbnail {
+    index: u64,
+    timestamp: Duration,
+    score: f32,
+    image: Frame,
+}
+
+/// 逐帧接收录像并生成缩略图拼版
+///
+/// 只保存被选中帧的缩略图，内存占用与录像长度无关。
+/// 均匀取帧时保留的候选不超过 `2 * count` 个，超出后隔一个丢弃并加倍抽样间隔。
+pub struct ContactSheetBuilder {
+    options: ContactSheetOptions,
+    fps: f64,
+    frames_seen: u64,
+    stride: u64,
+    thumbnails: Vec<Thumbnail>,
+    detector: SceneDetector,
+}
+
+impl ContactSheetBuilder {
+    /// `fps` 用于由帧序号推算时间戳
+    pub fn new(options: ContactSheetOptions, fps: f64) -> Self {
+        Self {
+            options,
+            fps,
+            frames_seen: 0,
+            stride: 1,
+            thumbnails: Vec::new(),
+            detector: SceneDetector::new(SceneDetectorOptions {
+                min_interval: 0,
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// 已接收的帧数
+    pub fn frames_seen(&self) -> u64 {
+        self.frames_seen
+    }
+
+    /// 接收下一帧，时间戳由帧序号和帧率推算
+    pub fn push(&mut self, frame: &Frame) -> Result<(), NV12Error> {
+        let timestamp = if self.fps > 0.0 {
+            Duration::from_secs_f64(self.frames_seen as f64 / self.fps)
+        } else {
+            Duration::ZERO
+        };
+        self.push_at(frame, timestamp)
+    }
+
+    /// 接收下一帧并指定其时间戳
+    pub fn push_at(&mut self, frame: &Frame, timestamp: Duration) -> Result<(), NV12Error> {
+        let index = self.frames_seen;
+        self.frames_seen += 1;
+        if self.options.count == 0 {
+            return Ok(());
+        }
+
+        match self.options.selection {
+            Selection::Even => {
+                if !index.is_multiple_of(self.stride) {
+                    return Ok(());
+                }
+                let image = self.thumbnail(frame)?;
+                self.thumbnails.push(Thumbnail {
+                    index,
+                    timestamp,
+                    score: 0.0,
+                    image,
+                });
+                if self.thumbnails.len() > self.options.count * 2 {
+                    let stride = self.stride;
+                    self.thumbnails.retain(|t| (t.index / stride).is_multiple_of(2));
+                    self.stride *= 2;
+                }
+            }
+            Selection::SceneChange { threshold } => {
+                // 第一帧的差异为 1.0，总会被保留
+                let score = self.detector.analyze(frame).sad;
+                if score < threshold {
+                    return Ok(());
+                }
+
+                if self.thumbnails.len() == self.options.count {
+                    let (weakest, lowest) = self
+                        .thumbnails
+                        .iter()
+                        .enumerate()
+                        .map(|(i, t)| (i, t.score))
+                        .min_by(|a, b| a.1.total_cmp(&b.1))
+                        .unwrap();
+                    if score <= lowest {
+                        return Ok(());
+                    }
+                    self.thumbnails.remove(weakest);
+                }
+                let image = self.thumbnail(frame)?;
+                self.thumbnails.push(Thumbnail {
+                    index,
+                    timestamp,
+                    score,
+                    image,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// 生成 BGRA 格式的拼版
+    pub fn finish(self) -> Result<Frame, NV12Error> {
+        let options = self.options;
+        let selected: Vec<&Thumbnail> = match options.selection {
+            Selection::Even => {
+                let kept = self.thumbnails.len();
+                let count = options.count.min(kept);
+                // 每段取中点，使首尾留白相同
+                (0..count)
+                    .map(|k| &self.thumbnails[(2 * k + 1) * kept / (2 * count)])
+                    .collect()
+            }
+            Selection::SceneChange { .. } => {
+                let mut selected: Vec<&Thumbnail> = self.thumbnails.iter().collect();
+                selected.sort_by_key(|t| t.index);
+                selected
+            }
+        };
+        if selected.is_empty() {
+            return Err(NV12Error::InvalidPlanes(
+                "no frames to build contact sheet from".to_string(),
+            ));
+        }
+
+        let (thumb_w, thumb_h) = options.thumb_size;
+        let spacing = options.spacing;
+        let columns = options.columns.clamp(1, selected.len());
+        let rows = selected.len().div_ceil(columns);
+        let width = columns * thumb_w + (columns + 1) * spacing;
+        let height = rows * thumb_h + (rows + 1) * spacing;
+
+        let [r, g, b] = options.background;
+        let mut sheet = Frame::new(
+            PixelFormat::Bgra,
+            width,
+            height,
+            [b, g, r, 255].repeat(width * height),
+        )?;
+        for (i, thumbnail) in selected.iter().enumerate() {
+            let x = spacing + (i % columns) * (thumb_w + spacing);
+            let y = spacing + (i / columns) * (thumb_h + spacing);
+            blit(&thumbnail.image, &mut sheet, x, y)?;
+            if options.timestamps {
+                let label = format_timestamp(thumbnail.timestamp);
+                draw_label(
+                    &mut sheet,
+                    x,
+                    y + thumb_h,
+                    &label,
+                    options.label_scale.max(1),
+                );
+            }
+        }
+        Ok(sheet)
+    }
+
+    /// 缩放到缩略图尺寸并转换为 BGRA
+    fn thumbnail(&self, frame: &Frame) -> Result<Frame, NV12Error> {
+        let (thumb_w, thumb_h) = self.options.thumb_size;
+        let scaled = scale_to_fit(frame, thumb_w, thumb_h, self.options.filter)?;
+        Ok(convert(&scaled, PixelFormat::Bgra, ColorMatrix::default()))
+    }
+}
+
+/// 由一组帧生成拼版
+pub fn contact_sheet(
+    frames: &[Frame],
+    fps: f64,
+    options: ContactSheetOptions,
+) -> Result<Frame, NV12Error> {
+    let mut builder = ContactSheetBuilder::new(options, fps);
+    for frame in frames {
+        builder.push(frame)?;
+    }
+    builder.finish()
+}
+
+/// 读取整个 Y4M 录像并生成拼版，帧率取自流头
+pub fn contact_sheet_from_y4m<R: BufRead>(
+    reader: Y4mReader<R>,
+    options: ContactSheetOptions,
+) -> Result<Frame, Y4mError> {
+    let header = *reader.header();
+    let fps = header.fps_num as f64 / header.fps_den as f64;
+    let mut builder = ContactSheetBuilder::new(options, fps);
+    for frame in reader {
+        builder.push(&frame?)?;
+    }
+    Ok(builder.finish()?)
+}
+
+/// 时间戳格式化为 `H:MM:SS.mmm`
+pub fn format_timestamp(timestamp: Duration) -> String {
+    let secs = timestamp.as_secs();
+    format!(
+        "{}:{:02}:{:02}.{:03}",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+        timestamp.subsec_millis()
+    )
+}
+
+/// 在 (x, bottom) 左下角绘制带半透明底色的白色文字
+fn draw_label(sheet: &mut Frame, x: usize, bottom: usize, text: &str, scale: usize) {
+    let (text_w, text_h) = font::text_size(text, scale);
+    let padding = scale * 2;
+    let (box_w, box_h) = (text_w + padding * 2, text_h + padding);
+    let top = bottom.saturating_sub(box_h);
+
+    let mut canvas = Canvas::new(sheet);
+    canvas.fill_rect(Rect::new(x, top, box_w, box_h), Paint::new([0, 0, 0], 0.6));
+    canvas.text(
+        x + padding,
+        top + padding,
+        text,
+        scale,
+        Paint::solid([255, 255, 255]),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gray_frame(value: u8) -> Frame {
+        let mut frame = Frame::black(PixelFormat::Nv12, 64, 36);
+        frame.planes_mut()[0].fill(value);
+        frame
+    }
+
+    fn options(selection: Selection) -> ContactSheetOptions {
+        ContactSheetOptions {
+            count: 4,
+            columns: 2,
+            thumb_size: (32, 18),
+            spacing: 2,
+            selection,
+            timestamps: false,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_even_selection() {
+        let frames: Vec<Frame> = (0..100).map(|i| gray_frame(16 + i as u8)).collect();
+        let mut builder = ContactSheetBuilder::new(options(Selection::Even), 25.0);
+        for frame in &frames {
+            builder.push(frame).unwrap();
+        }
+        // 抽样间隔随帧数加倍，候选数不超过 2 * count
+        assert!(builder.thumbnails.len() <= 8);
+        assert_eq!(builder.frames_seen(), 100);
+
+        let sheet = builder.finish().unwrap();
+        assert_eq!(sheet.format(), PixelFormat::Bgra);
+        assert_eq!((sheet.width(), sheet.height()), (70, 42));
+
+        // 四个缩略图亮度递增
+        let centers: Vec<u8> = [(18, 11), (52, 11), (18, 31), (52, 31)]
+            .iter()
+            .map(|&(x, y)| sheet.data()[(y * sheet.width() + x) * 4])
+            .collect();
+        assert!(centers.windows(2).all(|w| w[0] < w[1]), "{:?}", centers);
+    }
+
+    #[test]
+    fn test_scene_change_selection() {
+        let mut frames: Vec<Frame> = (0..10).map(|_| gray_frame(30)).collect();
+        frames.extend((0..10).map(|_| gray_frame(200)));
+
+        let mut builder =
+            ContactSheetBuilder::new(options(Selection::SceneChange { threshold: 0.1 }), 10.0);
+        for frame in &frames {
+            builder.push(frame).unwrap();
+        }
+        let indices: Vec<u64> = builder.thumbnails.iter().map(|t| t.index).collect();
+        assert_eq!(indices, vec![0, 10]);
+        assert_eq!(builder.thumbnails[1].timestamp, Duration::from_secs(1));
+
+        let sheet = builder.finish().unwrap();
+        assert_eq!((sheet.width(), sheet.height()), (70, 22));
+        assert!(contact_sheet(&[], 25.0, ContactSheetOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(
+            format_timestamp(Duration::from_millis(3_723_045)),
+            "1:02:03.045"
+        );
+        assert_eq!(format_timestamp(Duration::ZERO), "0:00:00.000");
+    }
+}
@@ -0,0 +1,230 @@
+use crate::frame::{Frame, PixelFormat};
+
+/// YUV 与 RGB 互转使用的色彩矩阵（限制范围）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorMatrix {
+    Bt601,
+    #[default]
+    Bt709,
+}
+
+impl ColorMatrix {
+    /// (Kr, Kb) 系数
+    fn coefficients(self) -> (f32, f32) {
+        match self {
+            ColorMatrix::Bt601 => (0.299, 0.114),
+            ColorMatrix::Bt709 => (0.2126, 0.0722),
+        }
+    }
+
+    /// 8 位限制范围 Y'CbCr 转 R'G'B'
+    pub fn yuv_to_rgb(self, y: u8, u: u8, v: u8) -> [u8; 3] {
+        let (kr, kb) = self.coefficients();
+        let kg = 1.0 - kr - kb;
+        let y = (y as f32 - 16.0) / 219.0;
+        let cb = (u as f32 - 128.0) / 224.0;
+        let cr = (v as f32 - 128.0) / 224.0;
+
+        let r = y + 2.0 * (1.0 - kr) * cr;
+        let b = y + 2.0 * (1.0 - kb) * cb;
+        let g = (y - kr * r - kb * b) / kg;
+        [r, g, b].map(|c| (c * 255.0).round().clamp(0.0, 255.0) as u8)
+    }
+
+    /// R'G'B' 转 8 位限制范围 Y'CbCr，返回未量化的 (Y, Cb, Cr)
+    pub fn rgb_to_yuv(self, rgb: [f32; 3]) -> [f32; 3] {
+        let (kr, kb) = self.coefficients();
+        let [r, g, b] = rgb.map(|c| c / 255.0);
+        let y = kr * r + (1.0 - kr - kb) * g + kb * b;
+        [
+            16.0 + 219.0 * y,
+            128.0 + 224.0 * (b - y) / (2.0 * (1.0 - kb)),
+            128.0 + 224.0 * (r - y) / (2.0 * (1.0 - kr)),
+        ]
+    }
+}
+
+fn quantize(value: f32) -> u8 {
+    value.round().clamp(0.0, 255.0) as u8
+}
+
+/// 转换为紧密排列的 8 位 RGBA
+pub fn to_rgba(frame: &Frame, matrix: ColorMatrix) -> Vec<u8> {
+    let (width, height) = (frame.width(), frame.height());
+    let mut rgba = vec![0u8; width * height * 4];
+
+    match frame.format() {
+        PixelFormat::Rgba => rgba.copy_from_slice(frame.data()),
+        PixelFormat::Bgra => {
+            for (src, dst) in frame.data().chunks_exact(4).zip(rgba.chunks_exact_mut(4)) {
+                dst.copy_from_slice(&[src[2], src[1], src[0], src[3]]);
+            }
+        }
+        PixelFormat::Nv12 | PixelFormat::I420 => {
+            let planes = frame.planes();
+            let chroma_width = frame.plane_info(1).width;
+            for y in 0..height {
+                for x in 0..width {
+                    let c = (y / 2) * chroma_width + x / 2;
+                    let (u, v) = match frame.format() {
+                        PixelFormat::Nv12 => (planes[1][c * 2], planes[1][c * 2 + 1]),
+                        _ => (planes[1][c], planes[2][c]),
+                    };
+                    let rgb = matrix.yuv_to_rgb(planes[0][y * width + x], u, v);
+                    let i = (y * width + x) * 4;
+                    rgba[i..i + 3].copy_from_slice(&rgb);
+                    rgba[i + 3] = 255;
+                }
+            }
+        }
+    }
+    rgba
+}
+
+/// 8 位限制范围亮度平面，YUV 格式直接复制 Y 平面
+pub fn to_luma(frame: &Frame, matrix: ColorMatrix) -> Vec<u8> {
+    let order = match frame.format() {
+        PixelFormat::Nv12 | PixelFormat::I420 => return frame.planes()[0].to_vec(),
+        PixelFormat::Bgra => [2, 1, 0],
+        PixelFormat::Rgba => [0, 1, 2],
+    };
+    frame
+        .data()
+        .chunks_exact(4)
+        .map(|p| quantize(matrix.rgb_to_yuv(order.map(|c| p[c] as f32))[0]))
+        .collect()
+}
+
+/// 由紧密排列的 8 位 RGBA 创建指定格式的帧，YUV 色度取 2x2 块平均
+pub fn from_rgba(
+    rgba: &[u8],
+    width: usize,
+    height: usize,
+    format: PixelFormat,
+    matrix: ColorMatrix,
+) -> Frame {
+    let mut frame = Frame::black(format, width, height);
+    match format {
+        PixelFormat::Rgba => frame
+            .data_mut()
+            .copy_from_slice(&rgba[..width * height * 4]),
+        PixelFormat::Bgra => {
+            for (src, dst) in rgba
+                .chunks_exact(4)
+                .zip(frame.data_mut().chunks_exact_mut(4))
+            {
+                dst.copy_from_slice(&[src[2], src[1], src[0], src[3]]);
+            }
+        }
+        PixelFormat::Nv12 | PixelFormat::I420 => {
+            let chroma = frame.plane_info(1);
+            let mut planes = frame.planes_mut();
+            let pixel = |x: usize, y: usize| -> [f32; 3] {
+                let i = (y * width + x) * 4;
+                [rgba[i] as f32, rgba[i + 1] as f32, rgba[i + 2] as f32]
+            };
+
+            for y in 0..height {
+                for x in 0..width {
+                    planes[0][y * width + x] = quantize(matrix.rgb_to_yuv(pixel(x, y))[0]);
+                }
+            }
+
+            for cy in 0..chroma.height {
+                for cx in 0..chroma.width {
+                    let mut sum = [0f32; 3];
+                    let mut count = 0.0;
+                    for y in (cy * 2)..(cy * 2 + 2).min(height) {
+                        for x in (cx * 2)..(cx * 2 + 2).min(width) {
+                            let p = pixel(x, y);
+                            sum.iter_mut().zip(p).for_each(|(s, c)| *s += c);
+                            count += 1.0;
+                        }
+                    }
+                    let yuv = matrix.rgb_to_yuv(sum.map(|s| s / count));
+                    let c = cy * chroma.width + cx;
+                    if format == PixelFormat::Nv12 {
+                        planes[1][c * 2] = quantize(yuv[1]);
+                        planes[1][c * 2 + 1] = quantize(yuv[2]);
+                    } else {
+                        planes[1][c] = quantize(yuv[1]);
+                        planes[2][c] = quantize(yuv[2]);
+                    }
+                }
+            }
+        }
+    }
+    frame
+}
+
+/// 在像素格式之间转换，NV12 与 I420 之间只重排色度平面
+pub fn convert(frame: &Frame, format: PixelFormat, matrix: ColorMatrix) -> Frame {
+    match (frame.format(), format) {
+        (from, to) if from == to => return frame.clone(),
+        (PixelFormat::Nv12, PixelFormat::I420) | (PixelFormat::I420, PixelFormat::Nv12) => {
+            return reorder_chroma(frame, format)
+        }
+        _ => {}
+    }
+    from_rgba(
+        &to_rgba(frame, matrix),
+        frame.width(),
+        frame.height(),
+        format,
+        matrix,
+    )
+}
+
+/// NV12 与 I420 互转：亮度原样复制，色度在交错与分离之间重排
+fn reorder_chroma(frame: &Frame, format: PixelFormat) -> Frame {
+    let mut output = Frame::black(format, frame.width(), frame.height());
+    let src = frame.planes();
+    let mut dst = output.planes_mut();
+    dst[0].copy_from_slice(src[0]);
+
+    if format == PixelFormat::I420 {
+        let (u, v) = dst.split_at_mut(2);
+        for (i, uv) in src[1].chunks_exact(2).enumerate() {
+            u[1][i] = uv[0];
+            v[0][i] = uv[1];
+        }
+    } else {
+        for (i, uv) in dst[1].chunks_exact_mut(2).enumerate() {
+            uv[0] = src[1][i];
+            uv[1] = src[2][i];
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_yuv_reference_colors() {
+        let matrix = ColorMatrix::Bt709;
+        assert_eq!(matrix.yuv_to_rgb(16, 128, 128), [0, 0, 0]);
+        assert_eq!(matrix.yuv_to_rgb(235, 128, 128), [255, 255, 255]);
+        assert_eq!(
+            matrix.rgb_to_yuv([255.0, 255.0, 255.0]).map(quantize),
+            [235, 128, 128]
+        );
+    }
+
+    #[test]
+    fn test_nv12_roundtrip() {
+        let rgba: Vec<u8> = [200u8, 40, 90, 255].repeat(6);
+        let nv12 = from_rgba(&rgba, 3, 2, PixelFormat::Nv12, ColorMatrix::Bt601);
+        assert_eq!(nv12.data().len(), PixelFormat::Nv12.frame_size(3, 2));
+
+        let back = to_rgba(&nv12, ColorMatrix::Bt601);
+        for (a, b) in back.iter().zip(&rgba) {
+            assert!((*a as i32 - *b as i32).abs() <= 2, "{} vs {}", a, b);
+        }
+
+        let bgra = convert(&nv12, PixelFormat::Bgra, ColorMatrix::Bt601);
+        assert_eq!(bgra.format(), PixelFormat::Bgra);
+        assert!((bgra.data()[2] as i32 - 200).abs() <= 2);
+    }
+}
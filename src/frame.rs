@@ -0,0 +1,217 @@
+use crate::img::PlanarFormat;
+use crate::nv12::NV12Error;
+
+/// 帧像素格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PixelFormat {
+    /// 8 位 B, G, R, A 打包
+    Bgra,
+    /// 8 位 R, G, B, A 打包
+    Rgba,
+    /// Y 平面 + UV 交错平面 (4:2:0)
+    Nv12,
+    /// Y, U, V 三个平面 (4:2:0)
+    I420,
+}
+
+/// 单个平面的尺寸信息
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlaneInfo {
+    /// 每行的采样点数
+    pub width: usize,
+    /// 行数
+    pub height: usize,
+    /// 每个采样点的字节数
+    pub channels: usize,
+}
+
+impl PlaneInfo {
+    /// 每行字节数
+    pub fn row_bytes(&self) -> usize {
+        self.width * self.channels
+    }
+
+    /// 平面字节数
+    pub fn size(&self) -> usize {
+        self.row_bytes() * self.height
+    }
+}
+
+impl PixelFormat {
+    /// 对应的平面YUV格式，打包RGB格式返回 `None`
+    pub fn planar(self) -> Option<PlanarFormat> {
+        match self {
+            PixelFormat::Nv12 => Some(PlanarFormat::NV12),
+            PixelFormat::I420 => Some(PlanarFormat::I420),
+            PixelFormat::Bgra | PixelFormat::Rgba => None,
+        }
+    }
+
+    /// 是否为YUV格式
+    pub fn is_yuv(self) -> bool {
+        self.planar().is_some()
+    }
+
+    /// 平面数量
+    pub fn plane_count(self) -> usize {
+        self.planar().map_or(1, PlanarFormat::plane_count)
+    }
+
+    /// 第 `index` 个平面的尺寸，色度尺寸向上取整
+    pub fn plane_info(self, index: usize, width: usize, height: usize) -> PlaneInfo {
+        match (self, index) {
+            (PixelFormat::Bgra | PixelFormat::Rgba, _) => PlaneInfo {
+                width,
+                height,
+                channels: 4,
+            },
+            (_, 0) => PlaneInfo {
+                width,
+                height,
+                channels: 1,
+            },
+            (PixelFormat::Nv12, _) => PlaneInfo {
+                width: width.div_ceil(2),
+                height: height.div_ceil(2),
+                channels: 2,
+            },
+            (PixelFormat::I420, _) => PlaneInfo {
+                width: width.div_ceil(2),
+                height: height.div_ceil(2),
+                channels: 1,
+            },
+        }
+    }
+
+    /// 紧密排列时整帧的字节数
+    pub fn frame_size(self, width: usize, height: usize) -> usize {
+        (0..self.plane_count())
+            .map(|i| self.plane_info(i, width, height).size())
+            .sum()
+    }
+
+    /// 第 `index` 个平面中黑色像素的字节值（YUV 为限制范围）
+    pub fn black_pixel(self, index: usize) -> &'static [u8] {
+        match (self, index) {
+            (PixelFormat::Bgra | PixelFormat::Rgba, _) => &[0, 0, 0, 255],
+            (_, 0) => &[16],
+            (PixelFormat::Nv12, _) => &[128, 128],
+            (PixelFormat::I420, _) => &[128],
+        }
+    }
+}
+
+/// 紧密排列（无stride）的视频帧
+///
+/// 字段不公开，保证 `data` 的长度总是等于 [`PixelFormat::frame_size`]。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    format: PixelFormat,
+    width: usize,
+    height: usize,
+    data: Vec<u8>,
+}
+
+impl Frame {
+    /// 由紧密排列的数据创建帧，多余的数据会被截断
+    pub fn new(
+        format: PixelFormat,
+        width: usize,
+        height: usize,
+        mut data: Vec<u8>,
+    ) -> Result<Self, NV12Error> {
+        let size = format.frame_size(width, height);
+        if data.len() < size {
+            return Err(NV12Error::InsufficientData(format!(
+                "{:?} {}x{} needs {} bytes, got {}",
+                format,
+                width,
+                height,
+                size,
+                data.len()
+            )));
+        }
+        data.truncate(size);
+        Ok(Self {
+            format,
+            width,
+            height,
+            data,
+        })
+    }
+
+    /// 创建纯黑帧
+    pub fn black(format: PixelFormat, width: usize, height: usize) -> Self {
+        let mut frame = Self {
+            format,
+            width,
+            height,
+            data: vec![0u8; format.frame_size(width, height)],
+        };
+        for (i, plane) in frame.planes_mut().into_iter().enumerate() {
+            let pixel = format.black_pixel(i);
+            for chunk in plane.chunks_exact_mut(pixel.len()) {
+                chunk.copy_from_slice(pixel);
+            }
+        }
+        frame
+    }
+
+    pub fn format(&self) -> PixelFormat {
+        self.format
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// 所有平面连续存放的数据
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// 可变数据，长度固定
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+
+    /// 第 `index` 个平面的尺寸
+    pub fn plane_info(&self, index: usize) -> PlaneInfo {
+        self.format.plane_info(index, self.width, self.height)
+    }
+
+    /// 各平面的只读切片
+    pub fn planes(&self) -> Vec<&[u8]> {
+        let mut planes = Vec::with_capacity(self.format.plane_count());
+        let mut rest = &self.data[..];
+        for i in 0..self.format.plane_count() {
+            let (plane, tail) = rest.split_at(self.plane_info(i).size());
+            planes.push(plane);
+            rest = tail;
+        }
+        planes
+    }
+
+    /// 各平面的可变切片
+    pub fn planes_mut(&mut self) -> Vec<&mut [u8]> {
+        let infos: Vec<PlaneInfo> = (0..self.format.plane_count())
+            .map(|i| self.plane_info(i))
+            .collect();
+        let mut planes = Vec::with_capacity(infos.len());
+        let mut rest = &mut self.data[..];
+        for info in infos {
+            let (plane, tail) = rest.split_at_mut(info.size());
+            planes.push(plane);
+            rest = tail;
+        }
+        planes
+    }
+}
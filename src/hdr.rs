@@ -0,0 +1,497 @@
+use crate::img::validate_plane;
+use crate::nv12::{NV12Error, NV12Organizer};
+
+/// 4x4 Bayer 有序抖动矩阵
+const BAYER_4X4: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+/// PQ (SMPTE ST 2084) 常量
+const PQ_M1: f32 = 0.159_301_76;
+const PQ_M2: f32 = 78.843_75;
+const PQ_C1: f32 = 0.835_937_5;
+const PQ_C2: f32 = 18.851_563;
+const PQ_C3: f32 = 18.6875;
+
+/// HLG (ARIB STD-B67) 常量
+const HLG_A: f32 = 0.178_832_77;
+const HLG_B: f32 = 0.284_668_92;
+const HLG_C: f32 = 0.559_910_7;
+
+/// BT.2020 到 BT.709 的线性光原色转换矩阵
+const BT2020_TO_BT709: [[f32; 3]; 3] = [
+    [1.660_491, -0.587_641_1, -0.072_849_9],
+    [-0.124_550_5, 1.132_899_9, -0.008_349_4],
+    [-0.018_150_8, -0.100_578_9, 1.118_729_7],
+];
+
+/// 输入信号的传递函数
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TransferFunction {
+    /// BT.709 SDR，不做色调映射
+    #[default]
+    Sdr,
+    /// SMPTE ST 2084 (HDR10)
+    Pq,
+    /// ARIB STD-B67 Hybrid Log-Gamma
+    Hlg,
+}
+
+/// 降低位深时的抖动方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DitherMode {
+    /// 直接四舍五入
+    None,
+    /// 4x4 Bayer 有序抖动
+    #[default]
+    Ordered,
+}
+
+impl DitherMode {
+    /// 位置 (x, y) 处的量化阈值，范围 [0, 1)
+    fn threshold(self, x: usize, y: usize) -> f32 {
+        match self {
+            DitherMode::None => 0.5,
+            DitherMode::Ordered => (BAYER_4X4[y & 3][x & 3] as f32 + 0.5) / 16.0,
+        }
+    }
+
+    /// 将 [0, 255] 范围内的浮点值量化为 8 位
+    fn quantize(self, value: f32, x: usize, y: usize) -> u8 {
+        (value + self.threshold(x, y)).floor().clamp(0.0, 255.0) as u8
+    }
+
+    /// 将高位对齐的 16 位采样量化为 8 位
+    fn quantize_u16(self, value: u16, x: usize, y: usize) -> u8 {
+        self.quantize(value as f32 / 256.0, x, y)
+    }
+}
+
+/// HDR 到 SDR 的色调映射器
+///
+/// 输入为 BT.2020 原色的非线性 R'G'B'，输出为 BT.709 原色、gamma 2.4 编码的 R'G'B'，范围均为 [0, 1]。
+#[derive(Debug, Clone, Copy)]
+pub struct ToneMapper {
+    transfer: TransferFunction,
+    /// 内容峰值亮度 (nits)
+    peak_nits: f32,
+    /// SDR 参考白亮度 (nits)
+    reference_white: f32,
+}
+
+impl ToneMapper {
+    pub fn new(transfer: TransferFunction) -> Self {
+        Self {
+            transfer,
+            peak_nits: 1000.0,
+            reference_white: 203.0,
+        }
+    }
+
+    /// 设置内容峰值亮度，默认 1000 nits
+    pub fn with_peak_nits(mut self, peak_nits: f32) -> Self {
+        self.peak_nits = peak_nits.max(1.0);
+        self
+    }
+
+    /// 设置 SDR 参考白亮度，默认 203 nits (BT.2408)
+    pub fn with_reference_white(mut self, reference_white: f32) -> Self {
+        self.reference_white = reference_white.max(1.0);
+        self
+    }
+
+    pub fn transfer(&self) -> TransferFunction {
+        self.transfer
+    }
+
+    /// 映射一个像素
+    pub fn map(&self, rgb: [f32; 3]) -> [f32; 3] {
+        let linear = match self.transfer {
+            TransferFunction::Sdr => return rgb,
+            TransferFunction::Pq => rgb.map(pq_eotf),
+            TransferFunction::Hlg => hlg_eotf(rgb, self.peak_nits),
+        };
+
+        // 以参考白为 1.0 的相对亮度
+        let scaled = linear.map(|c| c / self.reference_white);
+        let luma = 0.2627 * scaled[0] + 0.6780 * scaled[1] + 0.0593 * scaled[2];
+
+        // 扩展 Reinhard 压缩高光，再按曲线在参考白处的值归一化，使参考白映射到 SDR 白 1.0
+        let max_white = self.peak_nits / self.reference_white;
+        let curve = |l: f32| l * (1.0 + l / (max_white * max_white)) / (1.0 + l);
+        let mapped_luma = curve(luma) / curve(1.0);
+        let gain = if luma > 0.0 { mapped_luma / luma } else { 0.0 };
+
+        let bt2020 = scaled.map(|c| c * gain);
+        let mut out = [0.0f32; 3];
+        for (i, row) in BT2020_TO_BT709.iter().enumerate() {
+            let c = row[0] * bt2020[0] + row[1] * bt2020[1] + row[2] * bt2020[2];
+            out[i] = c.clamp(0.0, 1.0).powf(1.0 / 2.4);
+        }
+        out
+    }
+}
+
+/// PQ 非线性值转换为绝对亮度 (nits)
+fn pq_eotf(value: f32) -> f32 {
+    let p = value.clamp(0.0, 1.0).powf(1.0 / PQ_M2);
+    let num = (p - PQ_C1).max(0.0);
+    let den = PQ_C2 - PQ_C3 * p;
+    10000.0 * (num / den).powf(1.0 / PQ_M1)
+}
+
+/// HLG 非线性值转换为显示亮度 (nits)，包含反向 OETF 与系统 gamma 1.2 的 OOTF
+fn hlg_eotf(rgb: [f32; 3], peak_nits: f32) -> [f32; 3] {
+    let scene = rgb.map(|e| {
+        let e = e.clamp(0.0, 1.0);
+        if e <= 0.5 {
+            e * e / 3.0
+        } else {
+            (((e - HLG_C) / HLG_A).exp() + HLG_B) / 12.0
+        }
+    });
+    let luma = 0.2627 * scene[0] + 0.6780 * scene[1] + 0.0593 * scene[2];
+    let gain = peak_nits * luma.max(0.0).powf(0.2);
+    scene.map(|c| c * gain)
+}
+
+/// 限制范围 YCbCr 系数
+struct YuvRange {
+    y_offset: f32,
+    y_scale: f32,
+    c_scale: f32,
+}
+
+/// 10 位限制范围
+const RANGE_10BIT: YuvRange = YuvRange {
+    y_offset: 64.0,
+    y_scale: 876.0,
+    c_scale: 896.0,
+};
+
+/// BT.2020 非恒定亮度 Y'CbCr 转 R'G'B'
+fn bt2020_yuv_to_rgb(y: f32, cb: f32, cr: f32) -> [f32; 3] {
+    [
+        y + 1.4746 * cr,
+        y - 0.164_553 * cb - 0.571_353 * cr,
+        y + 1.8814 * cb,
+    ]
+}
+
+/// BT.709 R'G'B' 转 8 位限制范围 Y'CbCr，返回 Y 与未量化的 Cb/Cr
+fn bt709_rgb_to_yuv(rgb: [f32; 3]) -> [f32; 3] {
+    let y = 0.2126 * rgb[0] + 0.7152 * rgb[1] + 0.0722 * rgb[2];
+    [
+        16.0 + 219.0 * y,
+        128.0 + 224.0 * (rgb[2] - y) / 1.8556,
+        128.0 + 224.0 * (rgb[0] - y) / 1.5748,
+    ]
+}
+
+/// P010 (10 位 NV12，采样高位对齐存放于 16 位小端) 数据组织结构
+pub struct P010Organizer;
+
+impl P010Organizer {
+    /// 计算P010数据的采样数（每个采样 2 字节）
+    pub fn calculate_p010_samples(width: usize, height: usize) -> usize {
+        NV12Organizer::calculate_nv12_size(width, height)
+    }
+
+    /// 将带stride的P010字节数据组织为紧密排列的16位采样，stride 以字节为单位
+    pub fn organize_p010_data(
+        luminance_bytes: &[u8],
+        luminance_stride: usize,
+        chrominance_bytes: &[u8],
+        chrominance_stride: usize,
+        width: usize,
+        height: usize,
+    ) -> Result<Vec<u16>, NV12Error> {
+        let uv_row = NV12Organizer::uv_row_bytes(width);
+        let uv_height = height.div_ceil(2);
+
+        validate_plane(
+            "luminance",
+            luminance_bytes.len(),
+            luminance_stride,
+            width * 2,
+            height,
+        )?;
+        validate_plane(
+            "chrominance",
+            chrominance_bytes.len(),
+            chrominance_stride,
+            uv_row * 2,
+            uv_height,
+        )?;
+
+        let mut output = Vec::with_capacity(Self::calculate_p010_samples(width, height));
+        copy_samples(
+            luminance_bytes,
+            luminance_stride,
+            width,
+            height,
+            &mut output,
+        );
+        copy_samples(
+            chrominance_bytes,
+            chrominance_stride,
+            uv_row,
+            uv_height,
+            &mut output,
+        );
+        Ok(output)
+    }
+
+    /// 获取P010数据中Y和UV平面的切片
+    pub fn get_p010_planes(p010_data: &[u16], width: usize, height: usize) -> (&[u16], &[u16]) {
+        p010_data.split_at(width * height)
+    }
+
+    /// 降为8位NV12，不改变传递函数
+    pub fn downconvert_to_nv12(
+        p010_data: &[u16],
+        width: usize,
+        height: usize,
+        dither: DitherMode,
+    ) -> Result<Vec<u8>, NV12Error> {
+        Self::check_len(p010_data, width, height)?;
+
+        let uv_row = NV12Organizer::uv_row_bytes(width);
+        let mut nv12 = vec![0u8; p010_data.len()];
+        let (y_plane, uv_plane) = Self::get_p010_planes(p010_data, width, height);
+        let (y_out, uv_out) = nv12.split_at_mut(width * height);
+
+        for (i, (dst, &src)) in y_out.iter_mut().zip(y_plane).enumerate() {
+            *dst = dither.quantize_u16(src, i % width, i / width);
+        }
+        for (i, (dst, &src)) in uv_out.iter_mut().zip(uv_plane).enumerate() {
+            let x = i % uv_row;
+            *dst = dither.quantize_u16(src, x / 2, i / uv_row);
+        }
+
+        Ok(nv12)
+    }
+
+    /// 将BT.2020 HDR (PQ/HLG) P010 色调映射为 BT.709 SDR NV12
+    ///
+    /// `ToneMapper` 的传递函数为 [`TransferFunction::Sdr`] 时等同于 [`Self::downconvert_to_nv12`]。
+    pub fn tone_map_to_nv12(
+        p010_data: &[u16],
+        width: usize,
+        height: usize,
+        tone_mapper: &ToneMapper,
+        dither: DitherMode,
+    ) -> Result<Vec<u8>, NV12Error> {
+        if tone_mapper.transfer() == TransferFunction::Sdr {
+            return Self::downconvert_to_nv12(p010_data, width, height, dither);
+        }
+        Self::check_len(p010_data, width, height)?;
+
+        let uv_row = NV12Organizer::uv_row_bytes(width);
+        let mut nv12 = vec![0u8; p010_data.len()];
+        let (y_plane, uv_plane) = Self::get_p010_planes(p010_data, width, height);
+        let (y_out, uv_out) = nv12.split_at_mut(width * height);
+        let sample = |v: u16| (v >> 6) as f32;
+
+        // 以 2x2 块为单位：逐像素输出亮度，色度取块内平均
+        for by in 0..height.div_ceil(2) {
+            for bx in 0..width.div_ceil(2) {
+                let uv_index = by * uv_row + bx * 2;
+                let cb = (sample(uv_plane[uv_index]) - 512.0) / RANGE_10BIT.c_scale;
+                let cr = (sample(uv_plane[uv_index + 1]) - 512.0) / RANGE_10BIT.c_scale;
+
+                let mut sum = [0.0f32; 3];
+                let mut count = 0.0;
+                for y in (by * 2)..(by * 2 + 2).min(height) {
+                    for x in (bx * 2)..(bx * 2 + 2).min(width) {
+                        let luma = (sample(y_plane[y * width + x]) - RANGE_10BIT.y_offset)
+                            / RANGE_10BIT.y_scale;
+                        let rgb = tone_mapper.map(bt2020_yuv_to_rgb(luma, cb, cr));
+                        let yuv = bt709_rgb_to_yuv(rgb);
+                        y_out[y * width + x] = dither.quantize(yuv[0], x, y);
+                        for (s, c) in sum.iter_mut().zip(rgb) {
+                            *s += c;
+                        }
+                        count += 1.0;
+                    }
+                }
+
+                let yuv = bt709_rgb_to_yuv(sum.map(|s| s / count));
+                uv_out[uv_index] = dither.quantize(yuv[1], bx, by);
+                uv_out[uv_index + 1] = dither.quantize(yuv[2], bx, by);
+            }
+        }
+
+        Ok(nv12)
+    }
+
+    fn check_len(p010_data: &[u16], width: usize, height: usize) -> Result<(), NV12Error> {
+        if p010_data.len() < Self::calculate_p010_samples(width, height) {
+            return Err(NV12Error::InsufficientData(
+                "p010 buffer too small".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// 16 位打包像素的通道顺序
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel64Order {
+    Rgba,
+    Bgra,
+}
+
+/// 16 位每通道 RGBA/BGRA (RGBA64) 数据组织结构
+pub struct Rgba64Organizer;
+
+impl Rgba64Organizer {
+    /// 将带stride的16位小端像素组织为紧密排列的采样，stride 以字节为单位
+    pub fn organize_rgba64_data(
+        bytes: &[u8],
+        stride: usize,
+        width: usize,
+        height: usize,
+    ) -> Result<Vec<u16>, NV12Error> {
+        validate_plane("rgba64", bytes.len(), stride, width * 8, height)?;
+
+        let mut output = Vec::with_capacity(width * height * 4);
+        copy_samples(bytes, stride, width * 4, height, &mut output);
+        Ok(output)
+    }
+
+    /// 转换为8位BGRA，HDR内容按 `tone_mapper` 映射到SDR
+    pub fn to_bgra8(
+        samples: &[u16],
+        order: Channel64Order,
+        width: usize,
+        height: usize,
+        tone_mapper: &ToneMapper,
+        dither: DitherMode,
+    ) -> Result<Vec<u8>, NV12Error> {
+        if samples.len() < width * height * 4 {
+            return Err(NV12Error::InsufficientData(
+                "rgba64 buffer too small".to_string(),
+            ));
+        }
+
+        let mut bgra = vec![0u8; width * height * 4];
+        for (i, (src, dst)) in samples
+            .chunks_exact(4)
+            .zip(bgra.chunks_exact_mut(4))
+            .enumerate()
+        {
+            let (x, y) = (i % width, i / width);
+            let (r, g, b) = match order {
+                Channel64Order::Rgba => (src[0], src[1], src[2]),
+                Channel64Order::Bgra => (src[2], src[1], src[0]),
+            };
+
+            if tone_mapper.transfer() == TransferFunction::Sdr {
+                dst[0] = dither.quantize_u16(b, x, y);
+                dst[1] = dither.quantize_u16(g, x, y);
+                dst[2] = dither.quantize_u16(r, x, y);
+            } else {
+                let rgb = tone_mapper.map([r, g, b].map(|c| c as f32 / 65535.0));
+                dst[0] = dither.quantize(rgb[2] * 255.0, x, y);
+                dst[1] = dither.quantize(rgb[1] * 255.0, x, y);
+                dst[2] = dither.quantize(rgb[0] * 255.0, x, y);
+            }
+            dst[3] = (src[3] >> 8) as u8;
+        }
+
+        Ok(bgra)
+    }
+}
+
+/// 按行读取小端16位采样，忽略padding
+fn copy_samples(
+    bytes: &[u8],
+    stride: usize,
+    samples_per_row: usize,
+    rows: usize,
+    output: &mut Vec<u16>,
+) {
+    for y in 0..rows {
+        let row = &bytes[y * stride..y * stride + samples_per_row * 2];
+        output.extend(
+            row.chunks_exact(2)
+                .map(|b| u16::from_le_bytes([b[0], b[1]])),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn p010_bytes(samples: &[u16]) -> Vec<u8> {
+        samples.iter().flat_map(|s| s.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn test_p010_downconvert() {
+        let width = 2;
+        let height = 2;
+        // 10 位值左移 6 位存放
+        let y: Vec<u16> = [64u16, 940, 512, 1023].iter().map(|v| v << 6).collect();
+        let uv: Vec<u16> = [512u16 << 6, 512 << 6].to_vec();
+
+        let p010 = P010Organizer::organize_p010_data(
+            &p010_bytes(&y),
+            width * 2,
+            &p010_bytes(&uv),
+            width * 2,
+            width,
+            height,
+        )
+        .unwrap();
+        assert_eq!(p010.len(), 6);
+
+        let nv12 =
+            P010Organizer::downconvert_to_nv12(&p010, width, height, DitherMode::None).unwrap();
+        assert_eq!(nv12, vec![16, 235, 128, 255, 128, 128]);
+    }
+
+    #[test]
+    fn test_pq_tone_map_reference_white() {
+        // 203 nits 的 PQ 编码值约为 0.58
+        let mapper = ToneMapper::new(TransferFunction::Pq);
+        let white = mapper.map([0.5806, 0.5806, 0.5806]);
+        for c in white {
+            assert!((c - 1.0).abs() < 0.01, "{}", c);
+        }
+
+        // 参考白以下保留层次：约 100 nits 的 PQ 编码值约为 0.51
+        let mid = mapper.map([0.5081, 0.5081, 0.5081]);
+        for c in mid {
+            assert!(c > 0.7 && c < 0.95, "{}", c);
+        }
+
+        // 峰值亮度不应溢出
+        let peak = mapper.map([0.7518, 0.7518, 0.7518]);
+        for c in peak {
+            assert!((c - 1.0).abs() < 0.01, "{}", c);
+        }
+
+        assert_eq!(mapper.map([0.0; 3]), [0.0; 3]);
+    }
+
+    #[test]
+    fn test_hlg_tone_map_to_nv12() {
+        let width = 2;
+        let height = 2;
+        let mut p010 = vec![(700u16) << 6; 4];
+        p010.extend([512u16 << 6, 512 << 6]);
+
+        let mapper = ToneMapper::new(TransferFunction::Hlg);
+        let nv12 =
+            P010Organizer::tone_map_to_nv12(&p010, width, height, &mapper, DitherMode::Ordered)
+                .unwrap();
+        assert_eq!(nv12.len(), 6);
+        for &luma in &nv12[..4] {
+            assert!((16..=235).contains(&luma));
+        }
+        // 中性灰的色度保持在 128 附近
+        assert!((nv12[4] as i32 - 128).abs() <= 1);
+        assert!((nv12[5] as i32 - 128).abs() <= 1);
+    }
+}
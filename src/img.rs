@@ -1,88 +1,529 @@
-use image::{ImageBuffer, Rgb};
+use crate::convert::{to_rgba, ColorMatrix};
+use crate::frame::Frame;
+use crate::nv12::NV12Error;
+use image::codecs::bmp::BmpEncoder;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::{CompressionType, FilterType, PngEncoder};
+use image::codecs::qoi::QoiEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::{ColorType, ImageEncoder};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::thread::JoinHandle;
 
+/// 快照相关错误，可跨线程传递
+pub type SnapshotError = Box<dyn std::error::Error + Send + Sync>;
 
-pub fn save_frame_as_png(
-    data: &[u8],
-    width: u32,
-    height: u32,
-    frame_num: u32,
-) -> Result<(), Box<dyn std::error::Error>> {
+/// 快照图片格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotFormat {
+    /// `best` 为 true 时使用最高压缩率，否则优先速度
+    Png { best: bool },
+    /// 质量 1-100
+    Jpeg { quality: u8 },
+    /// 无损 WebP
+    WebP,
+    Bmp,
+    Qoi,
+}
+
+impl Default for SnapshotFormat {
+    fn default() -> Self {
+        SnapshotFormat::Png { best: false }
+    }
+}
 
-    let img: ImageBuffer<Rgb<u8>, Vec<u8>> =
-        ImageBuffer::from_raw(width, height, data.to_vec()).ok_or("Cannot create image buffer")?;
+impl SnapshotFormat {
+    /// 按文件扩展名推断格式，JPEG 默认质量 90
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "png" => Some(SnapshotFormat::Png { best: false }),
+            "jpg" | "jpeg" => Some(SnapshotFormat::Jpeg { quality: 90 }),
+            "webp" => Some(SnapshotFormat::WebP),
+            "bmp" => Some(SnapshotFormat::Bmp),
+            "qoi" => Some(SnapshotFormat::Qoi),
+            _ => None,
+        }
+    }
+}
 
-    let filename = format!("frame_{:03}.png", frame_num);
-    img.save(&filename)?;
-    println!("Saved frame: {}", filename);
+/// 将任意像素格式的帧编码为图片
+pub fn encode_snapshot(frame: &Frame, format: SnapshotFormat) -> Result<Vec<u8>, SnapshotError> {
+    let (width, height) = (frame.width() as u32, frame.height() as u32);
+    let rgba = to_rgba(frame, ColorMatrix::default());
+    let rgb = || -> Vec<u8> {
+        rgba.chunks_exact(4)
+            .flat_map(|p| [p[0], p[1], p[2]])
+            .collect()
+    };
 
+    let mut output = Vec::new();
+    match format {
+        SnapshotFormat::Png { best } => {
+            let compression = if best {
+                CompressionType::Best
+            } else {
+                CompressionType::Fast
+            };
+            PngEncoder::new_with_quality(&mut output, compression, FilterType::Adaptive)
+                .write_image(&rgba, width, height, ColorType::Rgba8)?;
+        }
+        SnapshotFormat::Jpeg { quality } => {
+            JpegEncoder::new_with_quality(&mut output, quality.clamp(1, 100))
+                .write_image(&rgb(), width, height, ColorType::Rgb8)?;
+        }
+        SnapshotFormat::WebP => {
+            WebPEncoder::new_lossless(&mut output)
+                .write_image(&rgba, width, height, ColorType::Rgba8)?;
+        }
+        SnapshotFormat::Bmp => {
+            BmpEncoder::new(&mut output).write_image(&rgb(), width, height, ColorType::Rgb8)?;
+        }
+        SnapshotFormat::Qoi => {
+            QoiEncoder::new(&mut output).write_image(&rgba, width, height, ColorType::Rgba8)?;
+        }
+    }
+    Ok(output)
+}
+
+/// 将帧保存到指定路径，`format` 为 `None` 时按扩展名推断
+pub fn save_snapshot(
+    frame: &Frame,
+    path: &Path,
+    format: Option<SnapshotFormat>,
+) -> Result<(), SnapshotError> {
+    let format = format
+        .or_else(|| SnapshotFormat::from_path(path))
+        .ok_or_else(|| format!("unknown snapshot format for {}", path.display()))?;
+    let bytes = encode_snapshot(frame, format)?;
+    std::fs::write(path, bytes)?;
     Ok(())
 }
 
-pub fn get_yuv_corrected(
-    lu_data: &Vec<u8>,    // 完整的Y平面数据
-    lu_stride: i32,     // Y平面的行跨度 (bytes per row)
-    ch_data: &Vec<u8>,    // 包含U平面后紧跟V平面的数据
-    ch_stride: i32,     // U和V平面各自的行跨度
-    width: i32,         // 图像宽度
-    height: i32,        // 图像高度
-) -> Vec<u8> {
-    let width_u = width as usize;
-    let height_u = height as usize;
-    let lu_stride_u = lu_stride as usize;
-    let ch_stride_u = ch_stride as usize;
+/// 快照输出位置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnapshotTarget {
+    File(PathBuf),
+    Memory,
+}
+
+/// 快照输出
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnapshotOutput {
+    File(PathBuf),
+    Memory(Vec<u8>),
+}
+
+/// 后台快照结果，`id` 对应 [`SnapshotWriter::submit`] 的返回值
+#[derive(Debug)]
+pub struct SnapshotResult {
+    pub id: u64,
+    pub outcome: Result<SnapshotOutput, SnapshotError>,
+}
+
+struct SnapshotJob {
+    id: u64,
+    frame: Frame,
+    format: SnapshotFormat,
+    target: SnapshotTarget,
+}
+
+/// 在后台线程编码和写入快照，避免阻塞采集
+pub struct SnapshotWriter {
+    jobs: Option<SyncSender<SnapshotJob>>,
+    results: Receiver<SnapshotResult>,
+    worker: Option<JoinHandle<()>>,
+    next_id: u64,
+    /// 已提交但结果尚未取走的快照数
+    pending: usize,
+}
 
-    let ch_width_u = (width / 2) as usize;
-    let ch_height_u = (height / 2) as usize;
+impl SnapshotWriter {
+    /// 创建后台写入线程，`queue_depth` 为最多排队的快照数
+    pub fn new(queue_depth: usize) -> Self {
+        let (jobs, job_rx) = mpsc::sync_channel::<SnapshotJob>(queue_depth.max(1));
+        let (result_tx, results) = mpsc::channel();
 
-    // 预估总大小并预分配内存
-    let y_plane_size = width_u * height_u; // 实际拷贝的Y数据大小
-    let uv_plane_size_each = ch_width_u * ch_height_u; // 实际拷贝的U或V数据大小
-    let mut result = Vec::with_capacity(y_plane_size + 2 * uv_plane_size_each);
+        let worker = std::thread::spawn(move || {
+            for job in job_rx {
+                let outcome = encode_snapshot(&job.frame, job.format).and_then(|bytes| {
+                    match job.target {
+                        SnapshotTarget::File(path) => {
+                            std::fs::write(&path, bytes)?;
+                            Ok(SnapshotOutput::File(path))
+                        }
+                        SnapshotTarget::Memory => Ok(SnapshotOutput::Memory(bytes)),
+                    }
+                });
+                if result_tx.send(SnapshotResult { id: job.id, outcome }).is_err() {
+                    break;
+                }
+            }
+        });
 
-    // 1. 复制 Y 平面数据
-    for r in 0..height_u {
-        let row_start = r * lu_stride_u;
-        let row_end = row_start + width_u;
-        if row_end <= lu_data.len() {
-            result.extend_from_slice(&lu_data[row_start..row_end]);
-        } else {
-            // 错误处理：数据不足
-            eprintln!("Error: Not enough luminance data for row {}", r);
-            return Vec::new(); // 或其他错误处理
+        Self {
+            jobs: Some(jobs),
+            results,
+            worker: Some(worker),
+            next_id: 0,
+            pending: 0,
         }
     }
 
-    // 2. 复制 U 平面数据
-    // 假设 U 平面数据在 ch_data 的开头
-    for r in 0..ch_height_u {
-        let row_start = r * ch_stride_u;
-        let row_end = row_start + ch_width_u;
-        if row_end <= ch_data.len() { // 确保不越界整个 ch_data
-            result.extend_from_slice(&ch_data[row_start..row_end]);
-        } else {
-            eprintln!("Error: Not enough U-plane data for row {}", r);
-            return Vec::new();
+    /// 提交快照，不会阻塞；队列已满时返回错误并丢弃该帧
+    pub fn submit(
+        &mut self,
+        frame: Frame,
+        format: SnapshotFormat,
+        target: SnapshotTarget,
+    ) -> Result<u64, SnapshotError> {
+        let id = self.next_id;
+        let job = SnapshotJob {
+            id,
+            frame,
+            format,
+            target,
+        };
+        match self.jobs.as_ref().map(|jobs| jobs.try_send(job)) {
+            Some(Ok(())) => {
+                self.next_id += 1;
+                self.pending += 1;
+                Ok(id)
+            }
+            Some(Err(TrySendError::Full(_))) => Err("snapshot queue is full".into()),
+            Some(Err(TrySendError::Disconnected(_))) | None => {
+                Err("snapshot worker has stopped".into())
+            }
         }
     }
 
-    // 3. 复制 V 平面数据
-    // V 平面数据紧跟在 U 平面数据之后
-    // U平面占用的总字节数（包括可能的 stride 填充）
-    let u_plane_total_bytes_in_ch = ch_height_u * ch_stride_u;
+    /// 获取已完成的快照结果，不阻塞
+    pub fn try_recv(&mut self) -> Option<SnapshotResult> {
+        let result = self.results.try_recv().ok()?;
+        self.pending -= 1;
+        Some(result)
+    }
+
+    /// 等待下一个快照结果
+    ///
+    /// 没有未完成的快照或后台线程已退出时返回 `None`，不会一直阻塞。
+    pub fn recv(&mut self) -> Option<SnapshotResult> {
+        if self.pending == 0 {
+            return None;
+        }
+        let result = self.results.recv().ok()?;
+        self.pending -= 1;
+        Some(result)
+    }
+}
+
+impl Drop for SnapshotWriter {
+    fn drop(&mut self) {
+        // 关闭队列后等待剩余快照写完
+        self.jobs.take();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// 平面YUV格式（4:2:0 采样）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlanarFormat {
+    /// Y 平面 + U 平面 + V 平面
+    I420,
+    /// Y 平面 + V 平面 + U 平面
+    YV12,
+    /// Y 平面 + UV 交错平面
+    NV12,
+}
+
+impl PlanarFormat {
+    /// 平面数量
+    pub fn plane_count(self) -> usize {
+        match self {
+            PlanarFormat::I420 | PlanarFormat::YV12 => 3,
+            PlanarFormat::NV12 => 2,
+        }
+    }
 
-    for r in 0..ch_height_u {
-        let v_row_start_in_ch_block = u_plane_total_bytes_in_ch + (r * ch_stride_u);
-        let v_row_end_in_ch_block = v_row_start_in_ch_block + ch_width_u;
-        if v_row_end_in_ch_block <= ch_data.len() {
-            result.extend_from_slice(&ch_data[v_row_start_in_ch_block..v_row_end_in_ch_block]);
-        } else {
-            eprintln!("Error: Not enough V-plane data for row {}", r);
-            return Vec::new();
+    /// 第 `index` 个平面每行有效字节数与行数，色度尺寸向上取整
+    pub fn plane_size(self, index: usize, width: usize, height: usize) -> (usize, usize) {
+        let ch_width = width.div_ceil(2);
+        let ch_height = height.div_ceil(2);
+        match (self, index) {
+            (_, 0) => (width, height),
+            (PlanarFormat::NV12, _) => (ch_width * 2, ch_height),
+            _ => (ch_width, ch_height),
         }
     }
 
-    result
+    /// 去除 stride 后整帧的字节数
+    pub fn frame_size(self, width: usize, height: usize) -> usize {
+        (0..self.plane_count())
+            .map(|i| {
+                let (row_bytes, rows) = self.plane_size(i, width, height);
+                row_bytes * rows
+            })
+            .sum()
+    }
+}
+
+/// 带行跨度的只读平面
+#[derive(Debug, Clone, Copy)]
+pub struct StridedPlane<'a> {
+    pub data: &'a [u8],
+    pub stride: usize,
 }
 
+/// 带行跨度的可写平面
+#[derive(Debug)]
+pub struct StridedPlaneMut<'a> {
+    pub data: &'a mut [u8],
+    pub stride: usize,
+}
 
+/// 校验单个平面的 stride 和缓冲区长度，`row_bytes` 为每行有效字节数
+pub(crate) fn validate_plane(
+    name: &str,
+    len: usize,
+    stride: usize,
+    row_bytes: usize,
+    rows: usize,
+) -> Result<(), NV12Error> {
+    if stride < row_bytes {
+        return Err(NV12Error::InvalidStride(format!("{} stride < row bytes", name)));
+    }
+    if rows > 0 && len < (rows - 1) * stride + row_bytes {
+        return Err(NV12Error::InsufficientData(format!("{} buffer too small", name)));
+    }
+    Ok(())
+}
 
+fn check_plane_count(format: PlanarFormat, count: usize) -> Result<(), NV12Error> {
+    if count != format.plane_count() {
+        return Err(NV12Error::InvalidPlanes(format!(
+            "{:?} expects {} planes, got {}",
+            format,
+            format.plane_count(),
+            count
+        )));
+    }
+    Ok(())
+}
+
+/// 将带 stride 的平面数据紧密排列到调用方提供的缓冲区，返回写入的字节数
+///
+/// 平面按格式自身的内存顺序给出（I420: Y,U,V；YV12: Y,V,U；NV12: Y,UV），输出保持相同顺序。
+pub fn destride_planes_into(
+    format: PlanarFormat,
+    planes: &[StridedPlane],
+    width: usize,
+    height: usize,
+    output: &mut [u8],
+) -> Result<usize, NV12Error> {
+    check_plane_count(format, planes.len())?;
+    let total = format.frame_size(width, height);
+    if output.len() < total {
+        return Err(NV12Error::InsufficientData("output buffer too small".to_string()));
+    }
+
+    for (i, plane) in planes.iter().enumerate() {
+        let (row_bytes, rows) = format.plane_size(i, width, height);
+        validate_plane(&format!("plane {}", i), plane.data.len(), plane.stride, row_bytes, rows)?;
+    }
+
+    let mut offset = 0;
+    for (i, plane) in planes.iter().enumerate() {
+        let (row_bytes, rows) = format.plane_size(i, width, height);
+        for r in 0..rows {
+            let src = r * plane.stride;
+            output[offset..offset + row_bytes].copy_from_slice(&plane.data[src..src + row_bytes]);
+            offset += row_bytes;
+        }
+    }
+
+    Ok(offset)
+}
+
+/// 将带 stride 的平面数据紧密排列为新的缓冲区
+pub fn destride_planes(
+    format: PlanarFormat,
+    planes: &[StridedPlane],
+    width: usize,
+    height: usize,
+) -> Result<Vec<u8>, NV12Error> {
+    let mut output = vec![0u8; format.frame_size(width, height)];
+    destride_planes_into(format, planes, width, height, &mut output)?;
+    Ok(output)
+}
+
+/// 将紧密排列的帧按目标平面的 stride 写回，padding 区域保持不变
+pub fn restride_planes(
+    format: PlanarFormat,
+    packed: &[u8],
+    width: usize,
+    height: usize,
+    planes: &mut [StridedPlaneMut],
+) -> Result<(), NV12Error> {
+    check_plane_count(format, planes.len())?;
+    if packed.len() < format.frame_size(width, height) {
+        return Err(NV12Error::InsufficientData("packed buffer too small".to_string()));
+    }
+
+    for (i, plane) in planes.iter().enumerate() {
+        let (row_bytes, rows) = format.plane_size(i, width, height);
+        validate_plane(&format!("plane {}", i), plane.data.len(), plane.stride, row_bytes, rows)?;
+    }
+
+    let mut offset = 0;
+    for (i, plane) in planes.iter_mut().enumerate() {
+        let (row_bytes, rows) = format.plane_size(i, width, height);
+        for r in 0..rows {
+            let dst = r * plane.stride;
+            plane.data[dst..dst + row_bytes].copy_from_slice(&packed[offset..offset + row_bytes]);
+            offset += row_bytes;
+        }
+    }
+
+    Ok(())
+}
+
+/// 将亮度平面与连续存放的色度数据紧密排列
+///
+/// `ch_data` 中色度平面按 `format` 的顺序依次存放，每个平面占 `色度行数 * ch_stride` 字节
+/// （NV12 只有一个 UV 交错平面）。
+pub fn get_yuv_corrected(
+    format: PlanarFormat,
+    lu_data: &[u8],
+    lu_stride: usize,
+    ch_data: &[u8],
+    ch_stride: usize,
+    width: usize,
+    height: usize,
+) -> Result<Vec<u8>, NV12Error> {
+    let luma = StridedPlane { data: lu_data, stride: lu_stride };
+    match format {
+        PlanarFormat::NV12 => {
+            let chroma = StridedPlane { data: ch_data, stride: ch_stride };
+            destride_planes(format, &[luma, chroma], width, height)
+        }
+        PlanarFormat::I420 | PlanarFormat::YV12 => {
+            let (_, ch_rows) = format.plane_size(1, width, height);
+            let split = (ch_rows * ch_stride).min(ch_data.len());
+            let (first, second) = ch_data.split_at(split);
+            let first = StridedPlane { data: first, stride: ch_stride };
+            let second = StridedPlane { data: second, stride: ch_stride };
+            destride_planes(format, &[luma, first, second], width, height)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::PixelFormat;
+
+    #[test]
+    fn test_encode_snapshot_formats() {
+        let frame = Frame::black(PixelFormat::Nv12, 6, 4);
+        for format in [
+            SnapshotFormat::Png { best: true },
+            SnapshotFormat::Jpeg { quality: 80 },
+            SnapshotFormat::WebP,
+            SnapshotFormat::Bmp,
+            SnapshotFormat::Qoi,
+        ] {
+            let bytes = encode_snapshot(&frame, format).unwrap();
+            let decoded = image::load_from_memory(&bytes).unwrap().to_rgb8();
+            assert_eq!(decoded.dimensions(), (6, 4), "{:?}", format);
+            assert!(decoded.pixels().all(|p| p.0.iter().all(|&c| c < 8)), "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn test_snapshot_writer_memory() {
+        let mut writer = SnapshotWriter::new(2);
+        let frame = Frame::black(PixelFormat::Bgra, 2, 2);
+        let id = writer
+            .submit(frame, SnapshotFormat::default(), SnapshotTarget::Memory)
+            .unwrap();
+
+        let result = writer.recv().unwrap();
+        assert_eq!(result.id, id);
+        match result.outcome.unwrap() {
+            SnapshotOutput::Memory(bytes) => assert!(bytes.starts_with(b"\x89PNG")),
+            other => panic!("unexpected output {:?}", other),
+        }
+        // 所有结果已取走，不再阻塞
+        assert!(writer.recv().is_none());
+        assert!(writer.try_recv().is_none());
+    }
+
+    #[test]
+    fn test_destride_i420_odd_size() {
+        let (width, height) = (5, 3);
+        let lu_stride = 8;
+        let ch_stride = 4;
+        let lu_data: Vec<u8> = (0..lu_stride * height).map(|i| i as u8).collect();
+        // 色度 3x2，U 在前 V 在后
+        let ch_data: Vec<u8> = (0..ch_stride * 2 * 2).map(|i| 100 + i as u8).collect();
+
+        let out = get_yuv_corrected(
+            PlanarFormat::I420,
+            &lu_data,
+            lu_stride,
+            &ch_data,
+            ch_stride,
+            width,
+            height,
+        )
+        .unwrap();
+
+        assert_eq!(out.len(), 5 * 3 + 2 * 3 * 2);
+        assert_eq!(&out[0..5], &[0, 1, 2, 3, 4]);
+        assert_eq!(&out[5..10], &[8, 9, 10, 11, 12]);
+        assert_eq!(&out[15..18], &[100, 101, 102]);
+        assert_eq!(&out[21..24], &[108, 109, 110]);
+    }
+
+    #[test]
+    fn test_restride_roundtrip_nv12() {
+        let (width, height) = (4, 2);
+        let packed: Vec<u8> = (0..PlanarFormat::NV12.frame_size(width, height))
+            .map(|i| i as u8)
+            .collect();
+        let mut y = vec![0xAAu8; 8 * 2];
+        let mut uv = vec![0xAAu8; 8];
+        {
+            let mut planes = [
+                StridedPlaneMut { data: &mut y, stride: 8 },
+                StridedPlaneMut { data: &mut uv, stride: 8 },
+            ];
+            restride_planes(PlanarFormat::NV12, &packed, width, height, &mut planes).unwrap();
+        }
+        assert_eq!(y[4], 0xAA);
+
+        let mut out = vec![0u8; packed.len()];
+        let written = destride_planes_into(
+            PlanarFormat::NV12,
+            &[StridedPlane { data: &y, stride: 8 }, StridedPlane { data: &uv, stride: 8 }],
+            width,
+            height,
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(written, packed.len());
+        assert_eq!(out, packed);
+    }
+
+    #[test]
+    fn test_destride_short_input() {
+        let result = get_yuv_corrected(PlanarFormat::YV12, &[0u8; 10], 4, &[0u8; 4], 2, 4, 4);
+        match result {
+            Err(NV12Error::InsufficientData(_)) => {}
+            other => panic!("Expected InsufficientData error, got {:?}", other),
+        }
+    }
+}
@@ -1,10 +1,25 @@
 mod img;
 mod nv12;
+mod hdr;
+mod frame;
+mod scale;
+mod transform;
+mod metrics;
+mod convert;
+mod y4m;
+mod clip;
+mod font;
+mod contact_sheet;
+mod overlay;
+mod watermark;
+mod annotation;
+mod redact;
+mod scene;
 mod screen;
 mod capture;
 use std::any::Any;
 use std::fmt::Debug;
-use obcoder::ObStream;
+use obcoder::{Encoder, ObStream, AVPixelFormat_AV_PIX_FMT_BGRA, AVPixelFormat_AV_PIX_FMT_YUV420P};
 use crate::capture::screencap::ScreenCapture;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -22,9 +37,18 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let mut frame_count = 0;
 
     println!("开始捕获视频流...");
-    let stream = ObStream::new(actual_width as u32, actual_height as u32, 2, format)?;
-    // let ptr = Arc::from(Mutex::from(stream));
-    // let mut video = ObEncoderVideo::new(ptr.clone())?;
+    let mut stream = ObStream::new(
+        actual_width as i32,
+        actual_height as i32,
+        AVPixelFormat_AV_PIX_FMT_BGRA,
+        AVPixelFormat_AV_PIX_FMT_YUV420P,
+    )?;
+    let mut video = Encoder::builder()
+        .size(actual_width as i32, actual_height as i32)
+        .fps(30)
+        .pixel_format(AVPixelFormat_AV_PIX_FMT_YUV420P)
+        .build()?;
+    println!("使用编码器: {}", video.codec_name());
 
     loop {
         match capture.capture_frame() {
@@ -40,12 +64,10 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 if frame_data.len() == 0 {
                     continue;
                 }
-
-                // if let Ok(ref mut mutex) = ptr.try_lock() {
-                //     mutex.write_frame(&frame_data)?;
-                // };
-                // let pkts = video.encode_available_frames()?;
-                // println!("send packets: {:?}", pkts.len());
+                stream.write_frame(&frame_data)?;
+                let mut frame = stream.read_frame()?;
+                let pkts = video.encode(&mut frame)?;
+                println!("send packets: {:?}", pkts.len());
 
                 // 演示用：捕获100帧后退出
                 if frame_count >= 100 {
@@ -59,8 +81,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     }
     capture.close();
-    // let last_pkt_vec = video.flush()?;
-    // println!("last packet vector: {:?}", last_pkt_vec.len());
+    let last_pkt_vec = video.flush()?;
+    println!("last packet vector: {:?}", last_pkt_vec.len());
     println!("视频流捕获完成！");
     Ok(())
 }
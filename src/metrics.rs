@@ -0,0 +1,401 @@
+use crate::frame::{Frame, PixelFormat};
+use crate::nv12::NV12Error;
+use image::{ImageBuffer, Rgb};
+
+/// SSIM 高斯窗口大小与标准差
+const SSIM_WINDOW: usize = 11;
+const SSIM_SIGMA: f32 = 1.5;
+const SSIM_C1: f32 = (0.01 * 255.0) * (0.01 * 255.0);
+const SSIM_C2: f32 = (0.03 * 255.0) * (0.03 * 255.0);
+
+/// MS-SSIM 各尺度权重
+const MS_SSIM_WEIGHTS: [f64; 5] = [0.0448, 0.2856, 0.3001, 0.2363, 0.1333];
+
+/// 单个分量（Y/U/V 或 B/G/R）的指标
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlaneMetrics {
+    pub name: &'static str,
+    pub mse: f64,
+    /// 完全相同时为正无穷
+    pub psnr: f64,
+    pub ssim: f64,
+}
+
+/// 两帧比较结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameMetrics {
+    pub planes: Vec<PlaneMetrics>,
+    /// 第一个分量（亮度或 B）的 MS-SSIM
+    pub ms_ssim: f64,
+}
+
+impl FrameMetrics {
+    /// 按名称查找分量
+    pub fn plane(&self, name: &str) -> Option<&PlaneMetrics> {
+        self.planes.iter().find(|p| p.name == name)
+    }
+
+    /// 所有分量合并后的 PSNR（按采样数加权的 MSE）
+    pub fn combined_psnr(&self, reference: &Frame) -> f64 {
+        let weights: Vec<f64> = components(reference)
+            .iter()
+            .map(|c| (c.width * c.height) as f64)
+            .collect();
+        let total: f64 = weights.iter().sum();
+        let mse = self
+            .planes
+            .iter()
+            .zip(&weights)
+            .map(|(p, w)| p.mse * w)
+            .sum::<f64>()
+            / total;
+        mse_to_psnr(mse)
+    }
+}
+
+/// 单个 8 位分量平面
+struct Component {
+    name: &'static str,
+    width: usize,
+    height: usize,
+    data: Vec<u8>,
+}
+
+/// 将帧拆分为独立的分量平面，RGB 格式忽略 alpha
+fn components(frame: &Frame) -> Vec<Component> {
+    let planes = frame.planes();
+    let split = |index: usize, names: &[&'static str]| -> Vec<Component> {
+        let info = frame.plane_info(index);
+        names
+            .iter()
+            .enumerate()
+            .map(|(c, &name)| Component {
+                name,
+                width: info.width,
+                height: info.height,
+                data: planes[index]
+                    .iter()
+                    .skip(c)
+                    .step_by(info.channels)
+                    .copied()
+                    .collect(),
+            })
+            .collect()
+    };
+
+    match frame.format() {
+        PixelFormat::Bgra => split(0, &["B", "G", "R"]),
+        PixelFormat::Rgba => split(0, &["R", "G", "B"]),
+        PixelFormat::Nv12 => {
+            let mut out = split(0, &["Y"]);
+            out.extend(split(1, &["U", "V"]));
+            out
+        }
+        PixelFormat::I420 => {
+            let mut out = split(0, &["Y"]);
+            out.extend(split(1, &["U"]));
+            out.extend(split(2, &["V"]));
+            out
+        }
+    }
+}
+
+fn check_same_shape(reference: &Frame, distorted: &Frame) -> Result<(), NV12Error> {
+    if reference.format() != distorted.format()
+        || reference.width() != distorted.width()
+        || reference.height() != distorted.height()
+    {
+        return Err(NV12Error::InvalidPlanes(format!(
+            "cannot compare {:?} {}x{} with {:?} {}x{}",
+            reference.format(),
+            reference.width(),
+            reference.height(),
+            distorted.format(),
+            distorted.width(),
+            distorted.height()
+        )));
+    }
+    Ok(())
+}
+
+/// 比较参考帧与失真帧，两帧的格式和尺寸必须一致
+pub fn compare(reference: &Frame, distorted: &Frame) -> Result<FrameMetrics, NV12Error> {
+    check_same_shape(reference, distorted)?;
+
+    let ref_components = components(reference);
+    let dis_components = components(distorted);
+
+    let planes = ref_components
+        .iter()
+        .zip(&dis_components)
+        .map(|(r, d)| {
+            let mse = mse(&r.data, &d.data);
+            PlaneMetrics {
+                name: r.name,
+                mse,
+                psnr: mse_to_psnr(mse),
+                ssim: ssim(&r.data, &d.data, r.width, r.height),
+            }
+        })
+        .collect();
+
+    let (r, d) = (&ref_components[0], &dis_components[0]);
+    Ok(FrameMetrics {
+        planes,
+        ms_ssim: ms_ssim(&r.data, &d.data, r.width, r.height),
+    })
+}
+
+/// 均方误差
+pub fn mse(a: &[u8], b: &[u8]) -> f64 {
+    if a.is_empty() {
+        return 0.0;
+    }
+    let sum: u64 = a
+        .iter()
+        .zip(b)
+        .map(|(&x, &y)| {
+            let d = x as i64 - y as i64;
+            (d * d) as u64
+        })
+        .sum();
+    sum as f64 / a.len() as f64
+}
+
+/// 8 位数据的 PSNR (dB)
+pub fn psnr(a: &[u8], b: &[u8]) -> f64 {
+    mse_to_psnr(mse(a, b))
+}
+
+fn mse_to_psnr(mse: f64) -> f64 {
+    if mse == 0.0 {
+        f64::INFINITY
+    } else {
+        10.0 * (255.0 * 255.0 / mse).log10()
+    }
+}
+
+/// 平均 SSIM，使用 11x11、σ=1.5 的高斯窗口
+pub fn ssim(a: &[u8], b: &[u8], width: usize, height: usize) -> f64 {
+    let a: Vec<f32> = a.iter().map(|&v| v as f32).collect();
+    let b: Vec<f32> = b.iter().map(|&v| v as f32).collect();
+    let (ssim, _) = ssim_components(&a, &b, width, height);
+    ssim
+}
+
+/// 多尺度 SSIM，图像过小时使用可用的尺度并重新归一化权重
+pub fn ms_ssim(a: &[u8], b: &[u8], width: usize, height: usize) -> f64 {
+    let mut a: Vec<f32> = a.iter().map(|&v| v as f32).collect();
+    let mut b: Vec<f32> = b.iter().map(|&v| v as f32).collect();
+    let (mut w, mut h) = (width, height);
+
+    let mut values = Vec::with_capacity(MS_SSIM_WEIGHTS.len());
+    for scale in 0..MS_SSIM_WEIGHTS.len() {
+        let (ssim, cs) = ssim_components(&a, &b, w, h);
+        let last = scale + 1 == MS_SSIM_WEIGHTS.len() || w / 2 < SSIM_WINDOW || h / 2 < SSIM_WINDOW;
+        values.push(if last { ssim } else { cs });
+        if last {
+            break;
+        }
+        a = downsample(&a, w, h);
+        b = downsample(&b, w, h);
+        w /= 2;
+        h /= 2;
+    }
+
+    let weights = &MS_SSIM_WEIGHTS[..values.len()];
+    let total: f64 = weights.iter().sum();
+    values
+        .iter()
+        .zip(weights)
+        .map(|(v, w)| v.max(0.0).powf(w / total))
+        .product()
+}
+
+/// 2x2 平均降采样
+fn downsample(src: &[f32], width: usize, height: usize) -> Vec<f32> {
+    let (w, h) = (width / 2, height / 2);
+    let mut out = Vec::with_capacity(w * h);
+    for y in 0..h {
+        for x in 0..w {
+            let i = y * 2 * width + x * 2;
+            out.push((src[i] + src[i + 1] + src[i + width] + src[i + width + 1]) / 4.0);
+        }
+    }
+    out
+}
+
+fn gaussian_kernel(size: usize) -> Vec<f32> {
+    let center = (size as f32 - 1.0) / 2.0;
+    let kernel: Vec<f32> = (0..size)
+        .map(|i| {
+            let d = i as f32 - center;
+            (-d * d / (2.0 * SSIM_SIGMA * SSIM_SIGMA)).exp()
+        })
+        .collect();
+    let sum: f32 = kernel.iter().sum();
+    kernel.into_iter().map(|k| k / sum).collect()
+}
+
+/// 可分离高斯滤波，只输出完整窗口覆盖的区域
+fn filter_valid(src: &[f32], width: usize, height: usize, kernel: &[f32]) -> Vec<f32> {
+    let k = kernel.len();
+    let (out_w, out_h) = (width + 1 - k, height + 1 - k);
+
+    let mut horizontal = vec![0f32; out_w * height];
+    for y in 0..height {
+        for x in 0..out_w {
+            let row = &src[y * width + x..][..k];
+            horizontal[y * out_w + x] = row.iter().zip(kernel).map(|(v, w)| v * w).sum();
+        }
+    }
+
+    let mut out = vec![0f32; out_w * out_h];
+    for y in 0..out_h {
+        for x in 0..out_w {
+            out[y * out_w + x] = kernel
+                .iter()
+                .enumerate()
+                .map(|(i, w)| horizontal[(y + i) * out_w + x] * w)
+                .sum();
+        }
+    }
+    out
+}
+
+/// 返回 (平均 SSIM, 平均对比度-结构项)
+fn ssim_components(a: &[f32], b: &[f32], width: usize, height: usize) -> (f64, f64) {
+    let size = SSIM_WINDOW.min(width).min(height);
+    if size == 0 {
+        return (1.0, 1.0);
+    }
+    let kernel = gaussian_kernel(size);
+
+    let product =
+        |x: &[f32], y: &[f32]| -> Vec<f32> { x.iter().zip(y).map(|(p, q)| p * q).collect() };
+    let mu_a = filter_valid(a, width, height, &kernel);
+    let mu_b = filter_valid(b, width, height, &kernel);
+    let aa = filter_valid(&product(a, a), width, height, &kernel);
+    let bb = filter_valid(&product(b, b), width, height, &kernel);
+    let ab = filter_valid(&product(a, b), width, height, &kernel);
+
+    let mut ssim_sum = 0.0f64;
+    let mut cs_sum = 0.0f64;
+    for i in 0..mu_a.len() {
+        let (ma, mb) = (mu_a[i], mu_b[i]);
+        let var_a = aa[i] - ma * ma;
+        let var_b = bb[i] - mb * mb;
+        let cov = ab[i] - ma * mb;
+
+        let cs = (2.0 * cov + SSIM_C2) / (var_a + var_b + SSIM_C2);
+        let luminance = (2.0 * ma * mb + SSIM_C1) / (ma * ma + mb * mb + SSIM_C1);
+        ssim_sum += (luminance * cs) as f64;
+        cs_sum += cs as f64;
+    }
+
+    let n = mu_a.len() as f64;
+    (ssim_sum / n, cs_sum / n)
+}
+
+/// 生成差异热力图：每个像素取各分量绝对差的最大值，乘以 `gain` 后映射为 黑-蓝-红-黄 色带
+pub fn difference_heatmap(
+    reference: &Frame,
+    distorted: &Frame,
+    gain: f32,
+) -> Result<ImageBuffer<Rgb<u8>, Vec<u8>>, NV12Error> {
+    check_same_shape(reference, distorted)?;
+
+    let (width, height) = (reference.width(), reference.height());
+    let ref_components = components(reference);
+    let dis_components = components(distorted);
+
+    let mut diff = vec![0u8; width * height];
+    for (r, d) in ref_components.iter().zip(&dis_components) {
+        // 色度平面按最近邻映射回亮度坐标
+        let (sx, sy) = (width.div_ceil(r.width), height.div_ceil(r.height));
+        for y in 0..height {
+            for x in 0..width {
+                let i = (y / sy) * r.width + x / sx;
+                let delta = r.data[i].abs_diff(d.data[i]);
+                let out = &mut diff[y * width + x];
+                *out = (*out).max(delta);
+            }
+        }
+    }
+
+    Ok(ImageBuffer::from_fn(width as u32, height as u32, |x, y| {
+        let delta = diff[y as usize * width + x as usize] as f32 * gain;
+        heat_color((delta / 255.0).clamp(0.0, 1.0))
+    }))
+}
+
+/// [0, 1] 映射到 黑 -> 蓝 -> 红 -> 黄
+fn heat_color(t: f32) -> Rgb<u8> {
+    let lerp = |a: f32, b: f32, t: f32| (a + (b - a) * t).round() as u8;
+    if t < 1.0 / 3.0 {
+        let t = t * 3.0;
+        Rgb([0, 0, lerp(0.0, 255.0, t)])
+    } else if t < 2.0 / 3.0 {
+        let t = (t - 1.0 / 3.0) * 3.0;
+        Rgb([lerp(0.0, 255.0, t), 0, lerp(255.0, 0.0, t)])
+    } else {
+        let t = (t - 2.0 / 3.0) * 3.0;
+        Rgb([255, lerp(0.0, 255.0, t), 0])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern(width: usize, height: usize) -> Frame {
+        let mut frame = Frame::black(PixelFormat::Nv12, width, height);
+        for (i, v) in frame.planes_mut()[0].iter_mut().enumerate() {
+            *v = ((i * 37) % 220 + 16) as u8;
+        }
+        frame
+    }
+
+    #[test]
+    fn test_identical_frames() {
+        let frame = pattern(32, 32);
+        let metrics = compare(&frame, &frame).unwrap();
+        assert_eq!(metrics.planes.len(), 3);
+        for plane in &metrics.planes {
+            assert_eq!(plane.psnr, f64::INFINITY);
+            assert!((plane.ssim - 1.0).abs() < 1e-6);
+        }
+        assert!((metrics.ms_ssim - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_psnr_known_error() {
+        let reference = pattern(32, 32);
+        let mut distorted = reference.clone();
+        for v in distorted.planes_mut()[0].iter_mut() {
+            *v += 2;
+        }
+
+        let metrics = compare(&reference, &distorted).unwrap();
+        let y = metrics.plane("Y").unwrap();
+        assert_eq!(y.mse, 4.0);
+        assert!((y.psnr - 42.11).abs() < 0.01);
+        assert!(y.ssim < 1.0 && y.ssim > 0.9);
+        assert_eq!(metrics.plane("U").unwrap().psnr, f64::INFINITY);
+    }
+
+    #[test]
+    fn test_heatmap_marks_difference() {
+        let reference = Frame::black(PixelFormat::Bgra, 4, 2);
+        let mut distorted = reference.clone();
+        distorted.data_mut()[2] = 255;
+
+        let heatmap = difference_heatmap(&reference, &distorted, 1.0).unwrap();
+        assert_eq!(heatmap.dimensions(), (4, 2));
+        assert_eq!(heatmap.get_pixel(0, 0), &Rgb([255, 255, 0]));
+        assert_eq!(heatmap.get_pixel(1, 0), &Rgb([0, 0, 0]));
+
+        let other = Frame::black(PixelFormat::Bgra, 2, 2);
+        assert!(compare(&reference, &other).is_err());
+    }
+}
@@ -3,6 +3,120 @@ use std::ptr;
 /// NV12数据组织结构
 pub struct NV12Organizer;
 
+/// 对齐后超出有效区域部分的填充方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PaddingMode {
+    /// 复制最近的边缘像素
+    #[default]
+    Replicate,
+    /// 填充黑色（限制范围：Y=16，UV=128）
+    Black,
+}
+
+/// NV12 输出缓冲区布局
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NV12Layout {
+    /// 有效图像宽
+    pub width: usize,
+    /// 有效图像高
+    pub height: usize,
+    /// Y 平面行跨度
+    pub y_stride: usize,
+    /// Y 平面行数（含对齐填充）
+    pub y_rows: usize,
+    /// UV 平面行跨度
+    pub uv_stride: usize,
+    /// UV 平面行数（含对齐填充）
+    pub uv_rows: usize,
+}
+
+impl NV12Layout {
+    /// 无填充的紧密布局，奇数宽高的色度向上取整
+    pub fn packed(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            y_stride: width,
+            y_rows: height,
+            uv_stride: NV12Organizer::uv_row_bytes(width),
+            uv_rows: height.div_ceil(2),
+        }
+    }
+
+    /// 行跨度按 `stride_alignment` 字节、行数按 `height_alignment` 行对齐的布局，
+    /// 两个平面使用相同的行跨度
+    pub fn aligned(
+        width: usize,
+        height: usize,
+        stride_alignment: usize,
+        height_alignment: usize,
+    ) -> Result<Self, NV12Error> {
+        for alignment in [stride_alignment, height_alignment] {
+            if !alignment.is_power_of_two() {
+                return Err(NV12Error::InvalidStride(format!(
+                    "alignment {} is not a power of two",
+                    alignment
+                )));
+            }
+        }
+
+        let stride = NV12Organizer::uv_row_bytes(width).next_multiple_of(stride_alignment);
+        let y_rows = height.next_multiple_of(height_alignment);
+        Ok(Self {
+            width,
+            height,
+            y_stride: stride,
+            y_rows,
+            uv_stride: stride,
+            uv_rows: y_rows.div_ceil(2),
+        })
+    }
+
+    /// 检查行跨度和行数能否容纳有效图像，调用方直接构造的布局需要先通过检查
+    pub fn validate(&self) -> Result<(), NV12Error> {
+        if self.y_stride < self.width {
+            return Err(NV12Error::InvalidStride("y_stride < width".to_string()));
+        }
+        if self.uv_stride < NV12Organizer::uv_row_bytes(self.width) {
+            return Err(NV12Error::InvalidStride("uv_stride < chroma row bytes".to_string()));
+        }
+        if self.y_rows < self.height || self.uv_rows < self.height.div_ceil(2) {
+            return Err(NV12Error::InvalidPlanes("plane rows < image height".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Y 平面字节数（UV 平面的起始偏移）
+    pub fn y_size(&self) -> usize {
+        self.y_stride * self.y_rows
+    }
+
+    /// UV 平面字节数
+    pub fn uv_size(&self) -> usize {
+        self.uv_stride * self.uv_rows
+    }
+
+    /// 整个缓冲区字节数
+    pub fn total_size(&self) -> usize {
+        self.y_size() + self.uv_size()
+    }
+}
+
+/// 单个平面的复制参数
+#[derive(Clone, Copy)]
+struct PlaneGeometry {
+    /// 每行有效字节数
+    row_bytes: usize,
+    /// 有效行数
+    rows: usize,
+    /// 输出行跨度
+    dst_stride: usize,
+    /// 输出行数（含对齐填充）
+    dst_rows: usize,
+    /// 单个像素占用的字节数（Y 为 1，UV 为 2）
+    pixel_bytes: usize,
+}
+
 impl NV12Organizer {
     /// 将带stride的YUV数据组织为NV12格式
     pub fn organize_nv12_data(
@@ -13,41 +127,121 @@ impl NV12Organizer {
         width: usize,
         height: usize,
     ) -> Result<Vec<u8>, NV12Error> {
+        let layout = NV12Layout::packed(width, height);
+        let mut nv12_output = vec![0u8; layout.total_size()];
+
+        Self::organize_nv12_into(
+            luminance_bytes,
+            luminance_stride,
+            chrominance_bytes,
+            chrominance_stride,
+            &layout,
+            PaddingMode::default(),
+            &mut nv12_output,
+        )?;
+
+        Ok(nv12_output)
+    }
+
+    /// 将带stride的YUV数据按给定布局（例如编码器要求的对齐）组织为NV12格式
+    pub fn organize_nv12_data_with_layout(
+        luminance_bytes: &[u8],
+        luminance_stride: usize,
+        chrominance_bytes: &[u8],
+        chrominance_stride: usize,
+        layout: &NV12Layout,
+        padding: PaddingMode,
+    ) -> Result<Vec<u8>, NV12Error> {
+        let mut nv12_output = vec![0u8; layout.total_size()];
+
+        Self::organize_nv12_into(
+            luminance_bytes,
+            luminance_stride,
+            chrominance_bytes,
+            chrominance_stride,
+            layout,
+            padding,
+            &mut nv12_output,
+        )?;
+
+        Ok(nv12_output)
+    }
+
+    /// 按给定布局将YUV数据写入调用方提供的缓冲区
+    pub fn organize_nv12_into(
+        luminance_bytes: &[u8],
+        luminance_stride: usize,
+        chrominance_bytes: &[u8],
+        chrominance_stride: usize,
+        layout: &NV12Layout,
+        padding: PaddingMode,
+        nv12_output: &mut [u8],
+    ) -> Result<(), NV12Error> {
         // 参数验证
+        layout.validate()?;
         Self::validate_parameters(
             luminance_bytes,
             luminance_stride,
             chrominance_bytes,
             chrominance_stride,
-            width,
-            height,
+            layout.width,
+            layout.height,
         )?;
 
-        let nv12_size = Self::calculate_nv12_size(width, height);
-        let mut nv12_output = vec![0u8; nv12_size];
+        if nv12_output.len() < layout.total_size() {
+            return Err(NV12Error::InsufficientData("output buffer too small".to_string()));
+        }
+
+        let (y_output, uv_output) = nv12_output.split_at_mut(layout.y_size());
 
-        Self::copy_luminance_plane(
+        let y_geometry = PlaneGeometry {
+            row_bytes: layout.width,
+            rows: layout.height,
+            dst_stride: layout.y_stride,
+            dst_rows: layout.y_rows,
+            pixel_bytes: 1,
+        };
+        Self::copy_plane(
             luminance_bytes,
             luminance_stride,
-            &mut nv12_output,
-            width,
-            height,
+            y_output,
+            &y_geometry,
+            Self::padding_value(padding, 16),
         )?;
 
-        Self::copy_chrominance_plane(
+        let uv_geometry = PlaneGeometry {
+            row_bytes: Self::uv_row_bytes(layout.width),
+            rows: layout.height.div_ceil(2),
+            dst_stride: layout.uv_stride,
+            dst_rows: layout.uv_rows,
+            pixel_bytes: 2,
+        };
+        Self::copy_plane(
             chrominance_bytes,
             chrominance_stride,
-            &mut nv12_output,
-            width,
-            height,
+            uv_output,
+            &uv_geometry,
+            Self::padding_value(padding, 128),
         )?;
 
-        Ok(nv12_output)
+        Ok(())
     }
 
-    /// 计算NV12数据总大小
+    /// 计算NV12数据总大小，奇数宽高的色度向上取整
     pub fn calculate_nv12_size(width: usize, height: usize) -> usize {
-        width * height * 3 / 2
+        NV12Layout::packed(width, height).total_size()
+    }
+
+    /// UV交错平面每行的有效字节数（色度宽度向上取整后乘2）
+    pub fn uv_row_bytes(width: usize) -> usize {
+        width.div_ceil(2) * 2
+    }
+
+    fn padding_value(padding: PaddingMode, black: u8) -> Option<u8> {
+        match padding {
+            PaddingMode::Replicate => None,
+            PaddingMode::Black => Some(black),
+        }
     }
 
     /// 参数验证
@@ -63,111 +257,120 @@ impl NV12Organizer {
             return Err(NV12Error::InvalidStride("luminance_stride < width".to_string()));
         }
 
-        if chrominance_stride < width {
-            return Err(NV12Error::InvalidStride("chrominance_stride < width".to_string()));
+        let uv_row_bytes = Self::uv_row_bytes(width);
+        if chrominance_stride < uv_row_bytes {
+            return Err(NV12Error::InvalidStride("chrominance_stride < chroma row bytes".to_string()));
         }
 
-        let required_y_size = height * luminance_stride;
-        if luminance_bytes.len() < required_y_size {
+        if height > 0 && luminance_bytes.len() < (height - 1) * luminance_stride + width {
             return Err(NV12Error::InsufficientData("luminance buffer too small".to_string()));
         }
 
-        let uv_height = height / 2;
-        let required_uv_size = uv_height * chrominance_stride;
-        if chrominance_bytes.len() < required_uv_size {
+        let uv_height = height.div_ceil(2);
+        if uv_height > 0
+            && chrominance_bytes.len() < (uv_height - 1) * chrominance_stride + uv_row_bytes
+        {
             return Err(NV12Error::InsufficientData("chrominance buffer too small".to_string()));
         }
 
         Ok(())
     }
 
-    /// 复制亮度平面
-    fn copy_luminance_plane(
-        luminance_bytes: &[u8],
-        luminance_stride: usize,
-        nv12_output: &mut [u8],
-        width: usize,
-        height: usize,
+    /// 复制一个平面，并按输出布局填充行尾与多余的行
+    ///
+    /// `black` 为 `None` 时复制边缘像素，否则用该值填充。
+    fn copy_plane(
+        src: &[u8],
+        src_stride: usize,
+        output: &mut [u8],
+        geometry: &PlaneGeometry,
+        black: Option<u8>,
     ) -> Result<(), NV12Error> {
-        let mut output_offset = 0;
-
-        for y in 0..height {
-            let src_offset = y * luminance_stride;
-            let src_end = src_offset + width;
-
-            if src_end > luminance_bytes.len() {
-                return Err(NV12Error::IndexOutOfBounds("luminance data".to_string()));
+        let PlaneGeometry {
+            row_bytes,
+            rows,
+            dst_stride,
+            dst_rows,
+            pixel_bytes,
+        } = *geometry;
+
+        for y in 0..rows {
+            let src_offset = y * src_stride;
+            let src_end = src_offset + row_bytes;
+
+            if src_end > src.len() {
+                return Err(NV12Error::IndexOutOfBounds("source plane".to_string()));
             }
 
-            let output_end = output_offset + width;
-            if output_end > nv12_output.len() {
+            let output_offset = y * dst_stride;
+            let output_end = output_offset + dst_stride;
+            if output_end > output.len() {
                 return Err(NV12Error::IndexOutOfBounds("output buffer".to_string()));
             }
 
             // 只复制有效的像素数据，忽略padding
-            nv12_output[output_offset..output_end]
-                .copy_from_slice(&luminance_bytes[src_offset..src_end]);
-
-            output_offset += width;
-        }
-
-        Ok(())
-    }
-
-    /// 复制色度平面
-    fn copy_chrominance_plane(
-        chrominance_bytes: &[u8],
-        chrominance_stride: usize,
-        nv12_output: &mut [u8],
-        width: usize,
-        height: usize,
-    ) -> Result<(), NV12Error> {
-        let y_plane_size = width * height;
-        let mut output_offset = y_plane_size;
-        let uv_height = height / 2;
-
-        for y in 0..uv_height {
-            let src_offset = y * chrominance_stride;
-            let src_end = src_offset + width;
-
-            if src_end > chrominance_bytes.len() {
-                return Err(NV12Error::IndexOutOfBounds("chrominance data".to_string()));
+            let row = &mut output[output_offset..output_end];
+            row[..row_bytes].copy_from_slice(&src[src_offset..src_end]);
+
+            match black {
+                Some(value) => row[row_bytes..].fill(value),
+                None if row_bytes >= pixel_bytes => {
+                    let (valid, pad) = row.split_at_mut(row_bytes);
+                    let edge = &valid[row_bytes - pixel_bytes..];
+                    for chunk in pad.chunks_mut(pixel_bytes) {
+                        chunk.copy_from_slice(&edge[..chunk.len()]);
+                    }
+                }
+                None => {}
             }
+        }
 
-            let output_end = output_offset + width;
-            if output_end > nv12_output.len() {
-                return Err(NV12Error::IndexOutOfBounds("output buffer".to_string()));
+        // 对齐产生的额外行
+        for y in rows..dst_rows {
+            let (filled, rest) = output.split_at_mut(y * dst_stride);
+            let row = &mut rest[..dst_stride];
+            match black {
+                Some(value) => row.fill(value),
+                None if y > 0 => row.copy_from_slice(&filled[(y - 1) * dst_stride..]),
+                None => row.fill(0),
             }
-
-            // 只复制有效的UV数据，忽略padding
-            nv12_output[output_offset..output_end]
-                .copy_from_slice(&chrominance_bytes[src_offset..src_end]);
-
-            output_offset += width;
         }
 
         Ok(())
     }
 
-    /// 获取NV12数据中Y和UV平面的切片
-    pub fn get_nv12_planes(nv12_data: &[u8], width: usize, height: usize) -> (&[u8], &[u8]) {
-        let y_plane_size = width * height;
-        let y_plane = &nv12_data[0..y_plane_size];
-        let uv_plane = &nv12_data[y_plane_size..];
-        (y_plane, uv_plane)
+    /// 按布局获取NV12数据中Y和UV平面的切片（含对齐填充）
+    pub fn get_nv12_planes<'a>(
+        nv12_data: &'a [u8],
+        layout: &NV12Layout,
+    ) -> Result<(&'a [u8], &'a [u8]), NV12Error> {
+        if nv12_data.len() < layout.total_size() {
+            return Err(NV12Error::InsufficientData("nv12 buffer too small".to_string()));
+        }
+        let (y_plane, rest) = nv12_data.split_at(layout.y_size());
+        Ok((y_plane, &rest[..layout.uv_size()]))
     }
 
-    /// 获取NV12数据中Y和UV平面的可变切片
-    pub fn get_nv12_planes_mut(nv12_data: &mut [u8], width: usize, height: usize) -> (&mut [u8], &mut [u8]) {
-        let y_plane_size = width * height;
-        let (y_plane, uv_plane) = nv12_data.split_at_mut(y_plane_size);
-        (y_plane, uv_plane)
+    /// 按布局获取NV12数据中Y和UV平面的可变切片（含对齐填充）
+    pub fn get_nv12_planes_mut<'a>(
+        nv12_data: &'a mut [u8],
+        layout: &NV12Layout,
+    ) -> Result<(&'a mut [u8], &'a mut [u8]), NV12Error> {
+        if nv12_data.len() < layout.total_size() {
+            return Err(NV12Error::InsufficientData("nv12 buffer too small".to_string()));
+        }
+        let (y_plane, rest) = nv12_data.split_at_mut(layout.y_size());
+        Ok((y_plane, &mut rest[..layout.uv_size()]))
     }
 }
 
 /// 零拷贝版本（使用不安全代码，性能更好）
 impl NV12Organizer {
     /// 零拷贝版本 - 直接操作指针
+    ///
+    /// # Safety
+    /// 输入指针需覆盖 `height` 行亮度与 `height.div_ceil(2)` 行色度，
+    /// 输出需至少 [`NV12Organizer::calculate_nv12_size`] 字节。
     pub unsafe fn organize_nv12_data_unchecked(
         luminance_bytes: *const u8,
         luminance_stride: usize,
@@ -187,11 +390,12 @@ impl NV12Organizer {
         }
 
         // 复制UV平面
-        let uv_height = height / 2;
+        let uv_height = height.div_ceil(2);
+        let uv_row_bytes = Self::uv_row_bytes(width);
         for y in 0..uv_height {
             let src_line = chrominance_bytes.add(y * chrominance_stride);
-            ptr::copy_nonoverlapping(src_line, output_ptr, width);
-            output_ptr = output_ptr.add(width);
+            ptr::copy_nonoverlapping(src_line, output_ptr, uv_row_bytes);
+            output_ptr = output_ptr.add(uv_row_bytes);
         }
     }
 }
@@ -202,6 +406,7 @@ pub enum NV12Error {
     InvalidStride(String),
     InsufficientData(String),
     IndexOutOfBounds(String),
+    InvalidPlanes(String),
 }
 
 impl std::fmt::Display for NV12Error {
@@ -210,6 +415,7 @@ impl std::fmt::Display for NV12Error {
             NV12Error::InvalidStride(msg) => write!(f, "Invalid stride: {}", msg),
             NV12Error::InsufficientData(msg) => write!(f, "Insufficient data: {}", msg),
             NV12Error::IndexOutOfBounds(msg) => write!(f, "Index out of bounds: {}", msg),
+            NV12Error::InvalidPlanes(msg) => write!(f, "Invalid planes: {}", msg),
         }
     }
 }
@@ -263,7 +469,8 @@ mod tests {
         assert_eq!(nv12_data.len(), width * height * 3 / 2);
 
         // 验证Y平面数据
-        let (y_plane, uv_plane) = NV12Organizer::get_nv12_planes(&nv12_data, width, height);
+        let layout = NV12Layout::packed(width, height);
+        let (y_plane, uv_plane) = NV12Organizer::get_nv12_planes(&nv12_data, &layout).unwrap();
         assert_eq!(y_plane.len(), width * height);
         assert_eq!(uv_plane.len(), width * height / 2);
 
@@ -302,6 +509,97 @@ mod tests {
             _ => panic!("Expected InvalidStride error"),
         }
     }
+
+    #[test]
+    fn test_odd_dimensions() {
+        let width = 5;
+        let height = 3;
+        let luminance_data: Vec<u8> = (0..width * height).map(|i| i as u8).collect();
+        // 色度 3x2 个采样点，每行 6 字节
+        let chrominance_data: Vec<u8> = (0..12).map(|i| 100 + i as u8).collect();
+
+        assert_eq!(NV12Organizer::calculate_nv12_size(width, height), 15 + 12);
+
+        let nv12_data = NV12Organizer::organize_nv12_data(
+            &luminance_data,
+            width,
+            &chrominance_data,
+            6,
+            width,
+            height,
+        )
+        .unwrap();
+
+        let layout = NV12Layout::packed(width, height);
+        let (y_plane, uv_plane) = NV12Organizer::get_nv12_planes(&nv12_data, &layout).unwrap();
+        assert_eq!(y_plane, &luminance_data[..]);
+        assert_eq!(uv_plane, &chrominance_data[..]);
+    }
+
+    #[test]
+    fn test_aligned_padding() {
+        let width = 6;
+        let height = 2;
+        let luminance_data: Vec<u8> = (0..12).map(|i| i as u8).collect();
+        let chrominance_data = vec![50, 60, 51, 61, 52, 62];
+
+        let layout = NV12Layout::aligned(width, height, 16, 2).unwrap();
+        let nv12_data = NV12Organizer::organize_nv12_data_with_layout(
+            &luminance_data,
+            width,
+            &chrominance_data,
+            width,
+            &layout,
+            PaddingMode::Replicate,
+        )
+        .unwrap();
+
+        assert_eq!(layout.y_stride, 16);
+        assert_eq!(nv12_data.len(), 16 * 3);
+        assert_eq!(&nv12_data[0..8], &[0, 1, 2, 3, 4, 5, 5, 5]);
+        assert_eq!(&nv12_data[32..38], &[50, 60, 51, 61, 52, 62]);
+        assert_eq!(&nv12_data[38..42], &[52, 62, 52, 62]);
+
+        let nv12_data = NV12Organizer::organize_nv12_data_with_layout(
+            &luminance_data,
+            width,
+            &chrominance_data,
+            width,
+            &layout,
+            PaddingMode::Black,
+        )
+        .unwrap();
+        assert_eq!(nv12_data[15], 16);
+        assert_eq!(nv12_data[47], 128);
+
+        let (y_plane, uv_plane) = NV12Organizer::get_nv12_planes(&nv12_data, &layout).unwrap();
+        assert_eq!((y_plane.len(), uv_plane.len()), (32, 16));
+        assert_eq!(uv_plane[0], 50);
+    }
+
+    #[test]
+    fn test_invalid_layout() {
+        let luminance_data = vec![0u8; 8];
+        let chrominance_data = vec![0u8; 4];
+        let mut layout = NV12Layout::packed(4, 2);
+        layout.y_stride = 2;
+        let mut output = vec![0u8; 64];
+        let result = NV12Organizer::organize_nv12_into(
+            &luminance_data,
+            4,
+            &chrominance_data,
+            4,
+            &layout,
+            PaddingMode::Black,
+            &mut output,
+        );
+        assert!(matches!(result, Err(NV12Error::InvalidStride(_))));
+
+        let mut layout = NV12Layout::packed(4, 2);
+        layout.uv_stride = 3;
+        assert!(matches!(layout.validate(), Err(NV12Error::InvalidStride(_))));
+        assert!(NV12Organizer::get_nv12_planes(&output[..4], &NV12Layout::packed(4, 2)).is_err());
+    }
 }
 
 /// 实际使用示例
@@ -329,9 +627,11 @@ pub fn example_usage() {
             println!("NV12 data size: {} bytes", nv12_data.len());
 
             // 获取Y和UV平面
-            let (y_plane, uv_plane) = NV12Organizer::get_nv12_planes(&nv12_data, width, height);
-            println!("Y plane size: {} bytes", y_plane.len());
-            println!("UV plane size: {} bytes", uv_plane.len());
+            let layout = NV12Layout::packed(width, height);
+            if let Ok((y_plane, uv_plane)) = NV12Organizer::get_nv12_planes(&nv12_data, &layout) {
+                println!("Y plane size: {} bytes", y_plane.len());
+                println!("UV plane size: {} bytes", uv_plane.len());
+            }
 
             // 现在可以使用nv12_data进行视频编码等操作
         }
@@ -0,0 +1,699 @@
+use crate::contact_sheet::format_timestamp;
+use crate::convert::ColorMatrix;
+use crate::font;
+use crate::frame::{Frame, PixelFormat};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// 矩形区域，坐标为亮度像素
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Rect {
+    pub fn new(x: usize, y: usize, width: usize, height: usize) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+}
+
+/// 绘制颜色 (R, G, B) 与不透明度 (0.0-1.0)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Paint {
+    pub color: [u8; 3],
+    pub opacity: f32,
+}
+
+impl Paint {
+    pub fn new(color: [u8; 3], opacity: f32) -> Self {
+        Self { color, opacity }
+    }
+
+    /// 不透明颜色
+    pub fn solid(color: [u8; 3]) -> Self {
+        Self::new(color, 1.0)
+    }
+}
+
+/// 覆盖度蒙版，`alpha` 按行存放 `width`x`height` 个覆盖度 (0-255)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mask {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+    pub alpha: Vec<u8>,
+}
+
+impl Mask {
+    pub fn new(x: usize, y: usize, width: usize, height: usize) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+            alpha: vec![0; width * height],
+        }
+    }
+
+    /// 帧坐标 (x, y) 处的覆盖度，蒙版外为 0
+    pub fn get(&self, x: usize, y: usize) -> u8 {
+        if x < self.x || y < self.y || x >= self.x + self.width || y >= self.y + self.height {
+            return 0;
+        }
+        self.alpha[(y - self.y) * self.width + (x - self.x)]
+    }
+
+    /// 以帧坐标设置覆盖度，取已有值与 `value` 的较大者
+    pub fn set(&mut self, x: usize, y: usize, value: u8) {
+        if x < self.x || y < self.y || x >= self.x + self.width || y >= self.y + self.height {
+            return;
+        }
+        let a = &mut self.alpha[(y - self.y) * self.width + (x - self.x)];
+        *a = (*a).max(value);
+    }
+
+    /// 描绘线段，端点为圆头，边缘做 1 像素抗锯齿；只影响蒙版范围内的像素
+    pub fn stroke_line(&mut self, from: (f32, f32), to: (f32, f32), thickness: f32) {
+        let radius = thickness.max(1.0) / 2.0;
+        let reach = radius + 1.0;
+        let bound = |lo: f32, origin: usize, size: usize| {
+            (lo.max(origin as f32) as usize).min(origin + size)
+        };
+        let x0 = bound((from.0.min(to.0) - reach).floor(), self.x, self.width);
+        let y0 = bound((from.1.min(to.1) - reach).floor(), self.y, self.height);
+        let x1 = bound((from.0.max(to.0) + reach).ceil() + 1.0, self.x, self.width);
+        let y1 = bound((from.1.max(to.1) + reach).ceil() + 1.0, self.y, self.height);
+
+        let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+        let length2 = dx * dx + dy * dy;
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let (px, py) = (x as f32 - from.0, y as f32 - from.1);
+                let t = if length2 > 0.0 {
+                    ((px * dx + py * dy) / length2).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                let distance = ((px - t * dx).powi(2) + (py - t * dy).powi(2)).sqrt();
+                let coverage = (radius + 0.5 - distance).clamp(0.0, 1.0);
+                self.set(x, y, (coverage * 255.0).round() as u8);
+            }
+        }
+    }
+}
+
+/// 在帧上绘制图形与文字，支持 BGRA、RGBA、NV12 和 I420
+///
+/// 图形先栅格化为覆盖度蒙版再合成；YUV 格式的色度按 2x2 块的平均覆盖度混合。
+pub struct Canvas<'a> {
+    frame: &'a mut Frame,
+    matrix: ColorMatrix,
+}
+
+impl<'a> Canvas<'a> {
+    pub fn new(frame: &'a mut Frame) -> Self {
+        Self {
+            frame,
+            matrix: ColorMatrix::default(),
+        }
+    }
+
+    /// 颜色转换到 YUV 时使用的色彩矩阵，默认 BT.709
+    pub fn with_color_matrix(mut self, matrix: ColorMatrix) -> Self {
+        self.matrix = matrix;
+        self
+    }
+
+    pub fn width(&self) -> usize {
+        self.frame.width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.frame.height()
+    }
+
+    /// 填充矩形
+    pub fn fill_rect(&mut self, rect: Rect, paint: Paint) {
+        let mut mask = Mask::new(rect.x, rect.y, rect.width, rect.height);
+        mask.alpha.fill(255);
+        self.fill_mask(&mask, paint);
+    }
+
+    /// 描边矩形，线宽向内侧扩展
+    pub fn stroke_rect(&mut self, rect: Rect, thickness: usize, paint: Paint) {
+        let t = thickness.min(rect.width).min(rect.height);
+        let mut mask = Mask::new(rect.x, rect.y, rect.width, rect.height);
+        for y in 0..rect.height {
+            for x in 0..rect.width {
+                if x < t || y < t || x >= rect.width - t || y >= rect.height - t {
+                    mask.alpha[y * rect.width + x] = 255;
+                }
+            }
+        }
+        self.fill_mask(&mask, paint);
+    }
+
+    /// 绘制线段，端点为圆头，边缘做 1 像素抗锯齿
+    pub fn line(&mut self, from: (usize, usize), to: (usize, usize), thickness: f32, paint: Paint) {
+        let points = [from, to].map(|(x, y)| (x as f32, y as f32));
+        self.polyline(&points, thickness, paint);
+    }
+
+    /// 绘制折线，各段合并为一个蒙版，半透明时交叠处不会加深
+    pub fn polyline(&mut self, points: &[(f32, f32)], thickness: f32, paint: Paint) {
+        let mask = self.polyline_mask(points, thickness);
+        self.fill_mask(&mask, paint);
+    }
+
+    /// 在 (x, y) 处绘制文字，`scale` 为字体放大倍数
+    pub fn text(&mut self, x: usize, y: usize, text: &str, scale: usize, paint: Paint) {
+        let (width, height) = font::text_size(text, scale);
+        let mut mask = Mask::new(x, y, width, height);
+        font::rasterize(text, scale, |tx, ty| {
+            mask.alpha[ty * width + tx] = 255;
+        });
+        self.fill_mask(&mask, paint);
+    }
+
+    /// 折线的覆盖度蒙版，只覆盖帧内的部分
+    pub fn polyline_mask(&self, points: &[(f32, f32)], thickness: f32) -> Mask {
+        if points.is_empty() {
+            return Mask::new(0, 0, 0, 0);
+        }
+        let reach = thickness.max(1.0) / 2.0 + 1.0;
+        let (mut min_x, mut min_y) = (f32::MAX, f32::MAX);
+        let (mut max_x, mut max_y) = (f32::MIN, f32::MIN);
+        for &(x, y) in points {
+            min_x = min_x.min(x);
+            min_y = min_y.min(y);
+            max_x = max_x.max(x);
+            max_y = max_y.max(y);
+        }
+        let x0 = ((min_x - reach).floor().max(0.0) as usize).min(self.frame.width());
+        let y0 = ((min_y - reach).floor().max(0.0) as usize).min(self.frame.height());
+        let x1 = ((max_x + reach).ceil().max(0.0) as usize + 1).min(self.frame.width());
+        let y1 = ((max_y + reach).ceil().max(0.0) as usize + 1).min(self.frame.height());
+
+        let mut mask = Mask::new(x0, y0, x1.saturating_sub(x0), y1.saturating_sub(y0));
+        if points.len() == 1 {
+            mask.stroke_line(points[0], points[0], thickness);
+        }
+        for segment in points.windows(2) {
+            mask.stroke_line(segment[0], segment[1], thickness);
+        }
+        mask
+    }
+
+    /// 按蒙版覆盖度用 `paint` 混合
+    pub fn fill_mask(&mut self, mask: &Mask, paint: Paint) {
+        let opacity = paint.opacity.clamp(0.0, 1.0);
+        if opacity == 0.0 {
+            return;
+        }
+        let color = paint.color.map(|c| c as f32);
+        let region = Rect::new(mask.x, mask.y, mask.width, mask.height);
+        self.composite(region, |x, y| {
+            (color, mask.get(x, y) as f32 / 255.0 * opacity)
+        });
+    }
+
+    /// 在 (x, y) 处混合非预乘的 RGBA 图像，`opacity` 与图像自身的透明度相乘
+    pub fn draw_rgba(
+        &mut self,
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+        rgba: &[u8],
+        opacity: f32,
+    ) {
+        let opacity = opacity.clamp(0.0, 1.0);
+        if opacity == 0.0 {
+            return;
+        }
+        self.composite(Rect::new(x, y, width, height), |px, py| {
+            let i = ((py - y) * width + (px - x)) * 4;
+            let p = &rgba[i..i + 4];
+            (
+                [p[0] as f32, p[1] as f32, p[2] as f32],
+                p[3] as f32 / 255.0 * opacity,
+            )
+        });
+    }
+
+    /// 在 `region` 内逐像素混合，`sample(x, y)` 返回该像素的 RGB 颜色与不透明度
+    ///
+    /// YUV 格式的色度取 2x2 块内按不透明度加权的平均颜色，以平均不透明度混合。
+    fn composite<F>(&mut self, region: Rect, sample: F)
+    where
+        F: Fn(usize, usize) -> ([f32; 3], f32),
+    {
+        let (width, height) = (self.frame.width(), self.frame.height());
+        let x_end = (region.x + region.width).min(width);
+        let y_end = (region.y + region.height).min(height);
+        if region.x >= x_end || region.y >= y_end {
+            return;
+        }
+
+        match self.frame.format() {
+            PixelFormat::Bgra | PixelFormat::Rgba => {
+                let bgra = self.frame.format() == PixelFormat::Bgra;
+                for y in region.y..y_end {
+                    for x in region.x..x_end {
+                        let (color, alpha) = sample(x, y);
+                        if alpha == 0.0 {
+                            continue;
+                        }
+                        let order = if bgra {
+                            [color[2], color[1], color[0]]
+                        } else {
+                            color
+                        };
+                        let i = (y * width + x) * 4;
+                        for (c, value) in self.frame.data_mut()[i..i + 3].iter_mut().zip(order) {
+                            *c = blend(*c, value, alpha);
+                        }
+                    }
+                }
+            }
+            PixelFormat::Nv12 | PixelFormat::I420 => {
+                let matrix = self.matrix;
+                let format = self.frame.format();
+                let chroma_width = self.frame.plane_info(1).width;
+                let mut planes = self.frame.planes_mut();
+                let inside =
+                    |x: usize, y: usize| x >= region.x && x < x_end && y >= region.y && y < y_end;
+
+                for cy in region.y / 2..y_end.div_ceil(2) {
+                    for cx in region.x / 2..x_end.div_ceil(2) {
+                        let mut weight = 0.0;
+                        let mut chroma = [0f32; 2];
+                        let mut count = 0.0;
+                        for y in cy * 2..(cy * 2 + 2).min(height) {
+                            for x in cx * 2..(cx * 2 + 2).min(width) {
+                                count += 1.0;
+                                if !inside(x, y) {
+                                    continue;
+                                }
+                                let (color, alpha) = sample(x, y);
+                                if alpha == 0.0 {
+                                    continue;
+                                }
+                                let [luma, cb, cr] = matrix.rgb_to_yuv(color);
+                                let p = &mut planes[0][y * width + x];
+                                *p = blend(*p, luma, alpha);
+                                chroma[0] += cb * alpha;
+                                chroma[1] += cr * alpha;
+                                weight += alpha;
+                            }
+                        }
+                        if weight == 0.0 {
+                            continue;
+                        }
+
+                        let [cb, cr] = chroma.map(|c| c / weight);
+                        let alpha = weight / count;
+                        let c = cy * chroma_width + cx;
+                        if format == PixelFormat::Nv12 {
+                            planes[1][c * 2] = blend(planes[1][c * 2], cb, alpha);
+                            planes[1][c * 2 + 1] = blend(planes[1][c * 2 + 1], cr, alpha);
+                        } else {
+                            planes[1][c] = blend(planes[1][c], cb, alpha);
+                            planes[2][c] = blend(planes[2][c], cr, alpha);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn blend(dst: u8, src: f32, alpha: f32) -> u8 {
+    (dst as f32 + (src - dst as f32) * alpha)
+        .round()
+        .clamp(0.0, 255.0) as u8
+}
+
+/// 图层在帧内的锚点
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Anchor {
+    #[default]
+    TopLeft,
+    TopCenter,
+    TopRight,
+    Center,
+    BottomLeft,
+    BottomCenter,
+    BottomRight,
+}
+
+/// 图层位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placement {
+    /// 相对锚点对齐，与帧边缘保持 `margin` 像素
+    Anchored { anchor: Anchor, margin: usize },
+    /// 左上角的绝对坐标
+    At { x: usize, y: usize },
+}
+
+impl Default for Placement {
+    fn default() -> Self {
+        Placement::Anchored {
+            anchor: Anchor::TopLeft,
+            margin: 8,
+        }
+    }
+}
+
+impl Placement {
+    /// `width`x`height` 的内容在 `frame_width`x`frame_height` 帧内的左上角坐标
+    pub fn resolve(
+        self,
+        width: usize,
+        height: usize,
+        frame_width: usize,
+        frame_height: usize,
+    ) -> (usize, usize) {
+        let (anchor, margin) = match self {
+            Placement::At { x, y } => return (x, y),
+            Placement::Anchored { anchor, margin } => (anchor, margin),
+        };
+        let left = margin;
+        let right = frame_width.saturating_sub(width + margin);
+        let center_x = frame_width.saturating_sub(width) / 2;
+        let top = margin;
+        let bottom = frame_height.saturating_sub(height + margin);
+        let center_y = frame_height.saturating_sub(height) / 2;
+        match anchor {
+            Anchor::TopLeft => (left, top),
+            Anchor::TopCenter => (center_x, top),
+            Anchor::TopRight => (right, top),
+            Anchor::Center => (center_x, center_y),
+            Anchor::BottomLeft => (left, bottom),
+            Anchor::BottomCenter => (center_x, bottom),
+            Anchor::BottomRight => (right, bottom),
+        }
+    }
+}
+
+/// 文字图层
+///
+/// `template` 中的占位符在每帧绘制时替换：`{time}` 为 UTC 墙上时间，
+/// `{elapsed}` 为录制时长，`{frame}` 为帧序号，`{host}` 为主机名。
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextLayer {
+    pub template: String,
+    pub placement: Placement,
+    pub scale: usize,
+    pub paint: Paint,
+    /// 文字底色，`None` 表示不绘制
+    pub background: Option<Paint>,
+    /// 底色四周的留白
+    pub padding: usize,
+}
+
+impl TextLayer {
+    pub fn new(template: impl Into<String>) -> Self {
+        Self {
+            template: template.into(),
+            placement: Placement::default(),
+            scale: 2,
+            paint: Paint::solid([255, 255, 255]),
+            background: Some(Paint::new([0, 0, 0], 0.6)),
+            padding: 4,
+        }
+    }
+
+    pub fn with_placement(mut self, placement: Placement) -> Self {
+        self.placement = placement;
+        self
+    }
+
+    pub fn with_scale(mut self, scale: usize) -> Self {
+        self.scale = scale.max(1);
+        self
+    }
+
+    pub fn with_paint(mut self, paint: Paint) -> Self {
+        self.paint = paint;
+        self
+    }
+
+    pub fn with_background(mut self, background: Option<Paint>) -> Self {
+        self.background = background;
+        self
+    }
+}
+
+/// 叠加层中的一项
+#[derive(Debug, Clone, PartialEq)]
+pub enum Layer {
+    Text(TextLayer),
+    /// 矩形，`thickness` 为 `None` 时填充
+    Rect {
+        rect: Rect,
+        thickness: Option<usize>,
+        paint: Paint,
+    },
+    Line {
+        from: (usize, usize),
+        to: (usize, usize),
+        thickness: f32,
+        paint: Paint,
+    },
+}
+
+/// 当前帧的信息，用于替换文字模板中的占位符
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameStamp {
+    pub index: u64,
+    /// 采集时的墙上时间
+    pub time: SystemTime,
+    /// 距录制开始的时长
+    pub elapsed: Duration,
+}
+
+impl FrameStamp {
+    pub fn new(index: u64, elapsed: Duration) -> Self {
+        Self {
+            index,
+            time: SystemTime::now(),
+            elapsed,
+        }
+    }
+}
+
+/// 叠加层：按添加顺序把各图层绘制到每一帧上
+#[derive(Debug, Clone, PartialEq)]
+pub struct Overlay {
+    layers: Vec<Layer>,
+    hostname: String,
+    matrix: ColorMatrix,
+}
+
+impl Default for Overlay {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Overlay {
+    pub fn new() -> Self {
+        Self {
+            layers: Vec::new(),
+            hostname: default_hostname(),
+            matrix: ColorMatrix::default(),
+        }
+    }
+
+    /// 替换 `{host}` 使用的主机名，默认取自环境变量
+    pub fn with_hostname(mut self, hostname: impl Into<String>) -> Self {
+        self.hostname = hostname.into();
+        self
+    }
+
+    pub fn with_color_matrix(mut self, matrix: ColorMatrix) -> Self {
+        self.matrix = matrix;
+        self
+    }
+
+    pub fn with_layer(mut self, layer: Layer) -> Self {
+        self.layers.push(layer);
+        self
+    }
+
+    pub fn push(&mut self, layer: Layer) {
+        self.layers.push(layer);
+    }
+
+    pub fn layers(&self) -> &[Layer] {
+        &self.layers
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    /// 展开文字模板中的占位符
+    pub fn render_text(&self, template: &str, stamp: &FrameStamp) -> String {
+        template
+            .replace("{time}", &format_utc(stamp.time))
+            .replace("{elapsed}", &format_timestamp(stamp.elapsed))
+            .replace("{frame}", &stamp.index.to_string())
+            .replace("{host}", &self.hostname)
+    }
+
+    /// 将所有图层绘制到帧上
+    pub fn apply(&self, frame: &mut Frame, stamp: &FrameStamp) {
+        let mut canvas = Canvas::new(frame).with_color_matrix(self.matrix);
+        for layer in &self.layers {
+            match layer {
+                Layer::Text(text) => {
+                    let content = self.render_text(&text.template, stamp);
+                    let (text_w, text_h) = font::text_size(&content, text.scale);
+                    let padding = if text.background.is_some() {
+                        text.padding
+                    } else {
+                        0
+                    };
+                    let (box_w, box_h) = (text_w + padding * 2, text_h + padding * 2);
+                    let (x, y) =
+                        text.placement
+                            .resolve(box_w, box_h, canvas.width(), canvas.height());
+                    if let Some(background) = text.background {
+                        canvas.fill_rect(Rect::new(x, y, box_w, box_h), background);
+                    }
+                    canvas.text(x + padding, y + padding, &content, text.scale, text.paint);
+                }
+                Layer::Rect {
+                    rect,
+                    thickness: None,
+                    paint,
+                } => canvas.fill_rect(*rect, *paint),
+                Layer::Rect {
+                    rect,
+                    thickness: Some(thickness),
+                    paint,
+                } => canvas.stroke_rect(*rect, *thickness, *paint),
+                Layer::Line {
+                    from,
+                    to,
+                    thickness,
+                    paint,
+                } => canvas.line(*from, *to, *thickness, *paint),
+            }
+        }
+    }
+}
+
+/// 主机名，依次尝试 COMPUTERNAME 与 HOSTNAME 环境变量
+pub fn default_hostname() -> String {
+    std::env::var("COMPUTERNAME")
+        .or_else(|_| std::env::var("HOSTNAME"))
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+/// 格式化为 `YYYY-MM-DD HH:MM:SS.mmm UTC`
+pub fn format_utc(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}.{:03} UTC",
+        year,
+        month,
+        day,
+        secs / 3600 % 24,
+        secs / 60 % 60,
+        secs % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+/// 1970-01-01 起的天数转换为公历 (年, 月, 日)
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_layer_on_bgra() {
+        let mut frame = Frame::black(PixelFormat::Bgra, 64, 32);
+        let overlay = Overlay::new().with_hostname("host").with_layer(Layer::Text(
+            TextLayer::new("{host}")
+                .with_scale(1)
+                .with_background(None)
+                .with_paint(Paint::solid([255, 0, 0]))
+                .with_placement(Placement::Anchored {
+                    anchor: Anchor::BottomRight,
+                    margin: 2,
+                }),
+        ));
+        overlay.apply(&mut frame, &FrameStamp::new(0, Duration::ZERO));
+
+        // 文字 24x10，位于 (38, 20) 起的区域内
+        let mut lit = Vec::new();
+        for (i, px) in frame.data().chunks_exact(4).enumerate() {
+            if px != [0, 0, 0, 255] {
+                assert_eq!(px, [0, 0, 255, 255]);
+                lit.push((i % 64, i / 64));
+            }
+        }
+        assert!(!lit.is_empty());
+        assert!(lit
+            .iter()
+            .all(|&(x, y)| (38..62).contains(&x) && (20..30).contains(&y)));
+    }
+
+    #[test]
+    fn test_fill_rect_nv12_blends_chroma() {
+        let mut frame = Frame::black(PixelFormat::Nv12, 4, 4);
+        let mut canvas = Canvas::new(&mut frame);
+        canvas.fill_rect(Rect::new(0, 0, 2, 2), Paint::solid([255, 255, 255]));
+        // 只覆盖 2x2 块的一半：色度不变（白色无色度），亮度按像素
+        canvas.fill_rect(Rect::new(2, 0, 1, 2), Paint::new([255, 0, 0], 0.5));
+
+        let planes = frame.planes();
+        assert_eq!(&planes[0][0..2], &[235, 235]);
+        assert_ne!(planes[0][2], 16);
+        assert_eq!(planes[0][3], 16);
+        assert_eq!(&planes[1][0..2], &[128, 128]);
+        // 红色抬高 Cr，半覆盖再乘以 0.5 不透明度
+        assert!(planes[1][3] > 128 && planes[1][3] < 180);
+    }
+
+    #[test]
+    fn test_template_and_utc() {
+        let stamp = FrameStamp {
+            index: 42,
+            time: UNIX_EPOCH + Duration::from_millis(1_700_000_000_123),
+            elapsed: Duration::from_secs(3661),
+        };
+        let overlay = Overlay::new().with_hostname("pc-01");
+        assert_eq!(
+            overlay.render_text("{host} #{frame} {elapsed} {time}", &stamp),
+            "pc-01 #42 1:01:01.000 2023-11-14 22:13:20.123 UTC"
+        );
+        assert_eq!(format_utc(UNIX_EPOCH), "1970-01-01 00:00:00.000 UTC");
+    }
+}
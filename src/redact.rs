@@ -0,0 +1,563 @@
+//! 敏感内容遮挡：在编码前对指定区域做马赛克或高斯模糊。
+//!
+//! 区域可以是固定矩形，也可以由模板图像（例如令牌输入框的截图）在每帧中匹配得到。
+
+use crate::convert::{to_luma, ColorMatrix};
+use crate::frame::Frame;
+use crate::nv12::NV12Error;
+use crate::overlay::Rect;
+
+/// 每个模板每帧最多匹配的区域数
+const MAX_MATCHES: usize = 16;
+/// 粗匹配阶段模板短边的目标尺寸
+const COARSE_TEMPLATE_SIZE: usize = 8;
+/// 粗匹配阈值相对精匹配阈值的放宽量，降采样会降低相关系数
+const COARSE_SLACK: f32 = 0.15;
+
+/// 遮挡方式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RedactMode {
+    /// 马赛克，`block_size` 为方块边长（亮度像素）；网格按帧坐标对齐，画面静止时不会闪烁
+    Pixelate { block_size: usize },
+    /// 高斯模糊；`sigma` 较小时文字仍可能辨认，遮挡凭据时应优先使用马赛克
+    Blur { sigma: f32 },
+}
+
+impl Default for RedactMode {
+    fn default() -> Self {
+        RedactMode::Pixelate { block_size: 16 }
+    }
+}
+
+/// 用于定位遮挡区域的模板
+///
+/// 先在降采样的亮度图上按归一化互相关粗匹配，再在原分辨率的邻域内精确定位。
+/// 帧只按固定网格降采样一次，模板为每种网格相位各保存一份降采样结果，
+/// 因此任意位置的匹配都能在粗匹配阶段与网格对齐。
+#[derive(Debug, Clone)]
+pub struct Template {
+    width: usize,
+    height: usize,
+    luma: Vec<u8>,
+    threshold: f32,
+    factor: usize,
+    /// 每种网格相位 (px, py) 对应的降采样模板，模板从 (px, py) 起裁剪后再降采样
+    coarse: Vec<(usize, usize, Luma)>,
+}
+
+impl Template {
+    /// `threshold` 为归一化互相关的下限 (0.0-1.0)，通常取 0.8 以上
+    pub fn new(image: &Frame, threshold: f32) -> Result<Self, NV12Error> {
+        let (width, height) = (image.width(), image.height());
+        if width < 2 || height < 2 {
+            return Err(NV12Error::InvalidPlanes(format!(
+                "template {}x{} is too small",
+                width, height
+            )));
+        }
+        let luma = to_luma(image, ColorMatrix::default());
+        let factor = (width.min(height) / COARSE_TEMPLATE_SIZE).max(1);
+        let full = Luma::new(&luma, width, height);
+        if full.downsample(factor).centered_norm() == 0.0 {
+            return Err(NV12Error::InvalidPlanes(
+                "template has no detail to match".to_string(),
+            ));
+        }
+        let coarse = (0..factor)
+            .flat_map(|py| (0..factor).map(move |px| (px, py)))
+            .map(|(px, py)| (px, py, full.crop(px, py).downsample(factor)))
+            .collect();
+        Ok(Self {
+            width,
+            height,
+            luma,
+            threshold,
+            factor,
+            coarse,
+        })
+    }
+
+    pub fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    /// 在帧中查找模板，返回匹配区域（按相似度从高到低，互不重叠）
+    pub fn find(&self, frame: &Frame) -> Vec<Rect> {
+        if frame.width() < self.width || frame.height() < self.height {
+            return Vec::new();
+        }
+        let full = Luma::new(
+            &to_luma(frame, ColorMatrix::default()),
+            frame.width(),
+            frame.height(),
+        );
+        let coarse = full.downsample(self.factor);
+        let template = Luma::new(&self.luma, self.width, self.height);
+
+        let mut candidates = Vec::new();
+        for (px, py, phase) in &self.coarse {
+            for (cx, cy, score) in coarse.correlate(phase, self.threshold - COARSE_SLACK) {
+                if cx * self.factor >= *px && cy * self.factor >= *py {
+                    candidates.push((cx * self.factor - px, cy * self.factor - py, score));
+                }
+            }
+        }
+        candidates.sort_by(|a, b| b.2.total_cmp(&a.2));
+
+        let mut matches: Vec<(Rect, f32)> = Vec::new();
+        for (x, y, _) in candidates {
+            if matches.len() == MAX_MATCHES {
+                break;
+            }
+            // 在原分辨率下的 ±1 邻域内精确定位
+            let x_range = x.saturating_sub(1)..=(x + 1).min(frame.width() - self.width);
+            let y_range = y.saturating_sub(1)..=(y + 1).min(frame.height() - self.height);
+            let mut best = (0, 0, f32::MIN);
+            for ry in y_range {
+                for rx in x_range.clone() {
+                    let score = full.ncc_at(&template, rx, ry);
+                    if score > best.2 {
+                        best = (rx, ry, score);
+                    }
+                }
+            }
+
+            let rect = Rect::new(best.0, best.1, self.width, self.height);
+            if best.2 >= self.threshold && !matches.iter().any(|(m, _)| overlaps(m, &rect)) {
+                matches.push((rect, best.2));
+            }
+        }
+        matches.sort_by(|a, b| b.1.total_cmp(&a.1));
+        matches.into_iter().map(|(rect, _)| rect).collect()
+    }
+}
+
+/// 遮挡区域
+#[derive(Debug, Clone)]
+pub enum Region {
+    Rect(Rect),
+    Template(Template),
+}
+
+/// 区域遮挡滤镜
+#[derive(Debug, Clone, Default)]
+pub struct RedactFilter {
+    mode: RedactMode,
+    regions: Vec<Region>,
+    padding: usize,
+}
+
+impl RedactFilter {
+    pub fn new(mode: RedactMode) -> Self {
+        Self {
+            mode,
+            regions: Vec::new(),
+            padding: 0,
+        }
+    }
+
+    pub fn with_rect(mut self, rect: Rect) -> Self {
+        self.regions.push(Region::Rect(rect));
+        self
+    }
+
+    pub fn with_template(mut self, template: Template) -> Self {
+        self.regions.push(Region::Template(template));
+        self
+    }
+
+    /// 每个区域向四周扩展的像素数，防止模板匹配偏差露出边缘
+    pub fn with_padding(mut self, padding: usize) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    pub fn regions(&self) -> &[Region] {
+        &self.regions
+    }
+
+    /// 遮挡所有区域，返回实际处理的矩形
+    pub fn apply(&self, frame: &mut Frame) -> Vec<Rect> {
+        let mut applied = Vec::new();
+        for region in &self.regions {
+            match region {
+                Region::Rect(rect) => applied.push(*rect),
+                Region::Template(template) => applied.extend(template.find(frame)),
+            }
+        }
+        for rect in &mut applied {
+            let x = rect.x.saturating_sub(self.padding);
+            let y = rect.y.saturating_sub(self.padding);
+            *rect = Rect::new(
+                x,
+                y,
+                rect.x + rect.width + self.padding - x,
+                rect.y + rect.height + self.padding - y,
+            );
+            redact(frame, *rect, self.mode);
+        }
+        applied
+    }
+}
+
+/// 对单个矩形区域做遮挡，超出帧的部分被忽略
+///
+/// YUV 格式的色度平面覆盖区域所在的全部 2x2 块。
+pub fn redact(frame: &mut Frame, rect: Rect, mode: RedactMode) {
+    let format = frame.format();
+    let (width, height) = (frame.width(), frame.height());
+    let x1 = (rect.x + rect.width).min(width);
+    let y1 = (rect.y + rect.height).min(height);
+    if rect.x >= x1 || rect.y >= y1 {
+        return;
+    }
+
+    for (i, plane) in frame.planes_mut().into_iter().enumerate() {
+        let info = format.plane_info(i, width, height);
+        let subsampled = i > 0 && format.is_yuv();
+        let region = if subsampled {
+            PlaneRegion {
+                x0: rect.x / 2,
+                y0: rect.y / 2,
+                x1: x1.div_ceil(2).min(info.width),
+                y1: y1.div_ceil(2).min(info.height),
+                width: info.width,
+                channels: info.channels,
+            }
+        } else {
+            PlaneRegion {
+                x0: rect.x,
+                y0: rect.y,
+                x1,
+                y1,
+                width: info.width,
+                channels: info.channels,
+            }
+        };
+
+        match mode {
+            RedactMode::Pixelate { block_size } => {
+                let block = if subsampled {
+                    block_size / 2
+                } else {
+                    block_size
+                };
+                pixelate_plane(plane, &region, block.max(1));
+            }
+            RedactMode::Blur { sigma } => {
+                let sigma = if subsampled { sigma / 2.0 } else { sigma };
+                if sigma > 0.0 {
+                    blur_plane(plane, &region, sigma);
+                }
+            }
+        }
+    }
+}
+
+/// 平面内的处理范围（采样坐标，左闭右开）
+struct PlaneRegion {
+    x0: usize,
+    y0: usize,
+    x1: usize,
+    y1: usize,
+    width: usize,
+    channels: usize,
+}
+
+fn pixelate_plane(plane: &mut [u8], region: &PlaneRegion, block: usize) {
+    let channels = region.channels;
+    let stride = region.width * channels;
+    let mut by = region.y0 / block * block;
+    while by < region.y1 {
+        let (ys, ye) = (by.max(region.y0), (by + block).min(region.y1));
+        let mut bx = region.x0 / block * block;
+        while bx < region.x1 {
+            let (xs, xe) = (bx.max(region.x0), (bx + block).min(region.x1));
+            let count = ((ye - ys) * (xe - xs)) as u32;
+            let mut sum = [0u32; 4];
+            for y in ys..ye {
+                for x in xs..xe {
+                    let i = y * stride + x * channels;
+                    for (s, v) in sum.iter_mut().zip(&plane[i..i + channels]) {
+                        *s += *v as u32;
+                    }
+                }
+            }
+            let average = sum.map(|s| ((s + count / 2) / count) as u8);
+            for y in ys..ye {
+                for x in xs..xe {
+                    let i = y * stride + x * channels;
+                    plane[i..i + channels].copy_from_slice(&average[..channels]);
+                }
+            }
+            bx += block;
+        }
+        by += block;
+    }
+}
+
+/// 可分离高斯模糊，只采样区域内的像素，区域外的内容不会渗入
+fn blur_plane(plane: &mut [u8], region: &PlaneRegion, sigma: f32) {
+    let channels = region.channels;
+    let stride = region.width * channels;
+    let (w, h) = (region.x1 - region.x0, region.y1 - region.y0);
+    let radius = (sigma * 3.0).ceil() as isize;
+    let kernel: Vec<f32> = (-radius..=radius)
+        .map(|k| (-((k * k) as f32) / (2.0 * sigma * sigma)).exp())
+        .collect();
+    let total: f32 = kernel.iter().sum();
+
+    let clamp = |v: isize, len: usize| v.clamp(0, len as isize - 1) as usize;
+    let mut horizontal = vec![0f32; w * h * channels];
+    for y in 0..h {
+        let row = (region.y0 + y) * stride;
+        for x in 0..w {
+            for c in 0..channels {
+                let mut acc = 0.0;
+                for (k, weight) in kernel.iter().enumerate() {
+                    let sx = region.x0 + clamp(x as isize + k as isize - radius, w);
+                    acc += plane[row + sx * channels + c] as f32 * weight;
+                }
+                horizontal[(y * w + x) * channels + c] = acc / total;
+            }
+        }
+    }
+    for y in 0..h {
+        let row = (region.y0 + y) * stride;
+        for x in 0..w {
+            for c in 0..channels {
+                let mut acc = 0.0;
+                for (k, weight) in kernel.iter().enumerate() {
+                    let sy = clamp(y as isize + k as isize - radius, h);
+                    acc += horizontal[(sy * w + x) * channels + c] * weight;
+                }
+                plane[row + (region.x0 + x) * channels + c] =
+                    (acc / total).round().clamp(0.0, 255.0) as u8;
+            }
+        }
+    }
+}
+
+fn overlaps(a: &Rect, b: &Rect) -> bool {
+    a.x < b.x + b.width && b.x < a.x + a.width && a.y < b.y + b.height && b.y < a.y + a.height
+}
+
+/// 带积分图的浮点亮度图
+#[derive(Debug, Clone)]
+struct Luma {
+    width: usize,
+    height: usize,
+    data: Vec<f32>,
+    /// 积分图与平方积分图，尺寸为 (width + 1) x (height + 1)
+    sum: Vec<f64>,
+    sum_sq: Vec<f64>,
+}
+
+impl Luma {
+    fn new(luma: &[u8], width: usize, height: usize) -> Self {
+        Self::from_f32(luma.iter().map(|&v| v as f32).collect(), width, height)
+    }
+
+    fn from_f32(data: Vec<f32>, width: usize, height: usize) -> Self {
+        let mut sum = vec![0f64; (width + 1) * (height + 1)];
+        let mut sum_sq = vec![0f64; (width + 1) * (height + 1)];
+        for y in 0..height {
+            let (mut row, mut row_sq) = (0f64, 0f64);
+            for x in 0..width {
+                let v = data[y * width + x] as f64;
+                row += v;
+                row_sq += v * v;
+                let i = (y + 1) * (width + 1) + x + 1;
+                sum[i] = sum[i - width - 1] + row;
+                sum_sq[i] = sum_sq[i - width - 1] + row_sq;
+            }
+        }
+        Self {
+            width,
+            height,
+            data,
+            sum,
+            sum_sq,
+        }
+    }
+
+    /// 去掉左侧 `x` 列和上方 `y` 行
+    fn crop(&self, x: usize, y: usize) -> Self {
+        if x == 0 && y == 0 {
+            return self.clone();
+        }
+        let (width, height) = (self.width - x, self.height - y);
+        let data = (y..self.height)
+            .flat_map(|row| {
+                self.data[row * self.width + x..(row + 1) * self.width]
+                    .iter()
+                    .copied()
+            })
+            .collect();
+        Self::from_f32(data, width, height)
+    }
+
+    /// 按 `factor`x`factor` 块取平均降采样
+    fn downsample(&self, factor: usize) -> Self {
+        if factor == 1 {
+            return self.clone();
+        }
+        let (width, height) = (self.width / factor, self.height / factor);
+        let area = (factor * factor) as f64;
+        let data = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                (self.window(&self.sum, x * factor, y * factor, factor, factor) / area) as f32
+            })
+            .collect();
+        Self::from_f32(data, width, height)
+    }
+
+    fn window(&self, table: &[f64], x: usize, y: usize, w: usize, h: usize) -> f64 {
+        let stride = self.width + 1;
+        table[(y + h) * stride + x + w] - table[y * stride + x + w] - table[(y + h) * stride + x]
+            + table[y * stride + x]
+    }
+
+    /// 去均值后的 L2 范数
+    fn centered_norm(&self) -> f64 {
+        let n = (self.width * self.height) as f64;
+        let sum = self.window(&self.sum, 0, 0, self.width, self.height);
+        let sum_sq = self.window(&self.sum_sq, 0, 0, self.width, self.height);
+        (sum_sq - sum * sum / n).max(0.0).sqrt()
+    }
+
+    /// 模板左上角位于 (x, y) 时的归一化互相关
+    fn ncc_at(&self, template: &Luma, x: usize, y: usize) -> f32 {
+        let (tw, th) = (template.width, template.height);
+        let n = (tw * th) as f64;
+        let t_mean = template.window(&template.sum, 0, 0, tw, th) / n;
+        let t_norm = template.centered_norm();
+
+        let w_sum = self.window(&self.sum, x, y, tw, th);
+        let w_sq = self.window(&self.sum_sq, x, y, tw, th);
+        let w_norm = (w_sq - w_sum * w_sum / n).max(0.0).sqrt();
+        if w_norm < 1e-6 || t_norm < 1e-6 {
+            return 0.0;
+        }
+
+        let mut cross = 0f64;
+        for ty in 0..th {
+            let row = (y + ty) * self.width + x;
+            for tx in 0..tw {
+                cross += self.data[row + tx] as f64 * (template.data[ty * tw + tx] as f64 - t_mean);
+            }
+        }
+        (cross / (w_norm * t_norm)) as f32
+    }
+
+    /// 所有得分不低于 `threshold` 的位置 (x, y, 得分)
+    fn correlate(&self, template: &Luma, threshold: f32) -> Vec<(usize, usize, f32)> {
+        if self.width < template.width || self.height < template.height {
+            return Vec::new();
+        }
+        let mut hits = Vec::new();
+        for y in 0..=self.height - template.height {
+            for x in 0..=self.width - template.width {
+                let score = self.ncc_at(template, x, y);
+                if score >= threshold {
+                    hits.push((x, y, score));
+                }
+            }
+        }
+        hits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::PixelFormat;
+
+    /// 伪随机纹理，避免模板在别处也能匹配
+    fn noise_frame(width: usize, height: usize) -> Frame {
+        let mut frame = Frame::black(PixelFormat::Nv12, width, height);
+        let mut state = 0x2545_f491u32;
+        for v in frame.planes_mut()[0].iter_mut() {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            *v = 16 + (state % 200) as u8;
+        }
+        frame
+    }
+
+    #[test]
+    fn test_pixelate_bgra() {
+        let data: Vec<u8> = (0..16u8).flat_map(|i| [i * 10, i, 0, 255]).collect();
+        let mut frame = Frame::new(PixelFormat::Bgra, 4, 4, data).unwrap();
+        let original = frame.clone();
+        redact(
+            &mut frame,
+            Rect::new(0, 0, 2, 2),
+            RedactMode::Pixelate { block_size: 2 },
+        );
+
+        // 像素 0, 1, 4, 5 取平均
+        let average = [25, 3, 0, 255];
+        for i in [0, 1, 4, 5] {
+            assert_eq!(&frame.data()[i * 4..i * 4 + 4], &average);
+        }
+        for i in [2, 3, 6, 7, 8, 15] {
+            assert_eq!(
+                &frame.data()[i * 4..i * 4 + 4],
+                &original.data()[i * 4..i * 4 + 4]
+            );
+        }
+    }
+
+    #[test]
+    fn test_blur_nv12_stays_inside_region() {
+        let mut frame = noise_frame(32, 32);
+        let original = frame.clone();
+        redact(
+            &mut frame,
+            Rect::new(8, 8, 16, 16),
+            RedactMode::Blur { sigma: 3.0 },
+        );
+
+        let variance = |luma: &[u8]| {
+            let values: Vec<f32> = (8..24)
+                .flat_map(|y| (8..24).map(move |x| luma[y * 32 + x] as f32))
+                .collect();
+            let mean = values.iter().sum::<f32>() / values.len() as f32;
+            values.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / values.len() as f32
+        };
+        assert!(variance(frame.planes()[0]) < variance(original.planes()[0]) / 4.0);
+        assert_eq!(
+            frame.planes()[0][7 * 32 + 7],
+            original.planes()[0][7 * 32 + 7]
+        );
+        assert_eq!(
+            frame.planes()[0][24 * 32 + 24],
+            original.planes()[0][24 * 32 + 24]
+        );
+    }
+
+    #[test]
+    fn test_template_match() {
+        let frame = noise_frame(96, 64);
+        let region = Rect::new(37, 21, 24, 16);
+        let mut template = Frame::black(PixelFormat::Nv12, region.width, region.height);
+        for y in 0..region.height {
+            let src = (region.y + y) * 96 + region.x;
+            template.planes_mut()[0][y * region.width..(y + 1) * region.width]
+                .copy_from_slice(&frame.planes()[0][src..src + region.width]);
+        }
+
+        let template = Template::new(&template, 0.9).unwrap();
+        assert_eq!(template.find(&frame), vec![region]);
+
+        let mut redacted = frame.clone();
+        let filter = RedactFilter::new(RedactMode::default())
+            .with_template(template)
+            .with_padding(2);
+        assert_eq!(filter.apply(&mut redacted), vec![Rect::new(35, 19, 28, 20)]);
+        assert!(Template::new(&Frame::black(PixelFormat::Nv12, 16, 16), 0.9).is_err());
+    }
+}
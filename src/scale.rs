@@ -0,0 +1,370 @@
+use crate::frame::{Frame, PixelFormat, PlaneInfo};
+use crate::nv12::NV12Error;
+use std::f32::consts::PI;
+
+/// 重采样滤波器
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScaleFilter {
+    /// 最近邻
+    Nearest,
+    /// 双线性
+    #[default]
+    Bilinear,
+    /// Catmull-Rom 双三次
+    Bicubic,
+    /// Lanczos (a = 3)
+    Lanczos3,
+    /// 区域平均，适合大比例缩小
+    Area,
+}
+
+impl ScaleFilter {
+    /// 缩放比例为 1 时的核半径
+    fn support(self) -> f32 {
+        match self {
+            ScaleFilter::Nearest | ScaleFilter::Area => 0.5,
+            ScaleFilter::Bilinear => 1.0,
+            ScaleFilter::Bicubic => 2.0,
+            ScaleFilter::Lanczos3 => 3.0,
+        }
+    }
+
+    fn kernel(self, x: f32) -> f32 {
+        let x = x.abs();
+        match self {
+            ScaleFilter::Nearest | ScaleFilter::Area => {
+                if x <= 0.5 {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            ScaleFilter::Bilinear => (1.0 - x).max(0.0),
+            ScaleFilter::Bicubic => {
+                const A: f32 = -0.5;
+                if x < 1.0 {
+                    ((A + 2.0) * x - (A + 3.0)) * x * x + 1.0
+                } else if x < 2.0 {
+                    ((A * x - 5.0 * A) * x + 8.0 * A) * x - 4.0 * A
+                } else {
+                    0.0
+                }
+            }
+            ScaleFilter::Lanczos3 => {
+                if x < 1e-6 {
+                    1.0
+                } else if x < 3.0 {
+                    let px = PI * x;
+                    3.0 * px.sin() * (px / 3.0).sin() / (px * px)
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+}
+
+/// 单个输出位置的采样权重
+struct Contribution {
+    start: usize,
+    weights: Vec<f32>,
+}
+
+/// 计算一维重采样的权重表
+fn contributions(src_len: usize, dst_len: usize, filter: ScaleFilter) -> Vec<Contribution> {
+    let scale = src_len as f32 / dst_len as f32;
+
+    if filter == ScaleFilter::Nearest {
+        return (0..dst_len)
+            .map(|i| Contribution {
+                start: (((i as f32 + 0.5) * scale) as usize).min(src_len - 1),
+                weights: vec![1.0],
+            })
+            .collect();
+    }
+
+    // 缩小时按比例放宽核，起到低通滤波的作用
+    let filter_scale = scale.max(1.0);
+    let support = filter.support() * filter_scale;
+
+    (0..dst_len)
+        .map(|i| {
+            let center = (i as f32 + 0.5) * scale;
+            let left = ((center - support).floor() as isize).max(0) as usize;
+            let right = ((center + support).ceil() as usize).min(src_len);
+
+            let mut weights: Vec<f32> = (left..right)
+                .map(|j| filter.kernel((j as f32 + 0.5 - center) / filter_scale))
+                .collect();
+            let sum: f32 = weights.iter().sum();
+            if sum.abs() > f32::EPSILON {
+                weights.iter_mut().for_each(|w| *w /= sum);
+            } else {
+                // 放大时区域滤波可能落在两个采样点之间
+                let nearest = (center as usize).min(src_len - 1);
+                return Contribution {
+                    start: nearest,
+                    weights: vec![1.0],
+                };
+            }
+
+            Contribution {
+                start: left,
+                weights,
+            }
+        })
+        .collect()
+}
+
+/// 可分离的两遍重采样：先水平后垂直
+fn resample_plane(
+    src: &[u8],
+    src_info: PlaneInfo,
+    dst: &mut [u8],
+    dst_info: PlaneInfo,
+    filter: ScaleFilter,
+) {
+    let channels = src_info.channels;
+    let horizontal = contributions(src_info.width, dst_info.width, filter);
+    let vertical = contributions(src_info.height, dst_info.height, filter);
+
+    // 水平方向：src_h x dst_w
+    let tmp_row = dst_info.width * channels;
+    let mut tmp = vec![0f32; tmp_row * src_info.height];
+    for y in 0..src_info.height {
+        let src_row = &src[y * src_info.row_bytes()..][..src_info.row_bytes()];
+        let tmp_line = &mut tmp[y * tmp_row..][..tmp_row];
+        for (x, contrib) in horizontal.iter().enumerate() {
+            for c in 0..channels {
+                let mut acc = 0.0;
+                for (k, w) in contrib.weights.iter().enumerate() {
+                    acc += src_row[(contrib.start + k) * channels + c] as f32 * w;
+                }
+                tmp_line[x * channels + c] = acc;
+            }
+        }
+    }
+
+    // 垂直方向：dst_h x dst_w
+    let dst_row = dst_info.row_bytes();
+    for (y, contrib) in vertical.iter().enumerate() {
+        let dst_line = &mut dst[y * dst_row..][..dst_row];
+        for (i, out) in dst_line.iter_mut().enumerate() {
+            let mut acc = 0.0;
+            for (k, w) in contrib.weights.iter().enumerate() {
+                acc += tmp[(contrib.start + k) * tmp_row + i] * w;
+            }
+            *out = acc.round().clamp(0.0, 255.0) as u8;
+        }
+    }
+}
+
+/// 将帧缩放到指定尺寸，不保持宽高比
+pub fn scale_frame(
+    frame: &Frame,
+    width: usize,
+    height: usize,
+    filter: ScaleFilter,
+) -> Result<Frame, NV12Error> {
+    if width == 0 || height == 0 || frame.width() == 0 || frame.height() == 0 {
+        return Err(NV12Error::InvalidPlanes(format!(
+            "cannot scale {}x{} to {}x{}",
+            frame.width(),
+            frame.height(),
+            width,
+            height
+        )));
+    }
+    if frame.width() == width && frame.height() == height {
+        return Ok(frame.clone());
+    }
+
+    let mut output = Frame::black(frame.format(), width, height);
+    for (i, (src, dst)) in frame
+        .planes()
+        .into_iter()
+        .zip(output.planes_mut())
+        .enumerate()
+    {
+        resample_plane(
+            src,
+            frame.plane_info(i),
+            dst,
+            frame.format().plane_info(i, width, height),
+            filter,
+        );
+    }
+    Ok(output)
+}
+
+/// 在 `width`x`height` 内保持宽高比时的缩放尺寸与偏移：(宽, 高, x, y)
+///
+/// YUV 格式的尺寸与偏移均为偶数，保证色度对齐，因此目标尺寸至少为 2。
+/// 源或目标尺寸不满足要求时返回错误。
+pub fn fit_rect(
+    format: PixelFormat,
+    src_width: usize,
+    src_height: usize,
+    width: usize,
+    height: usize,
+) -> Result<(usize, usize, usize, usize), NV12Error> {
+    let min_size = if format.is_yuv() { 2 } else { 1 };
+    if width < min_size || height < min_size || src_width == 0 || src_height == 0 {
+        return Err(NV12Error::InvalidPlanes(format!(
+            "cannot fit {}x{} into {}x{}",
+            src_width, src_height, width, height
+        )));
+    }
+    let scale = (width as f64 / src_width as f64).min(height as f64 / src_height as f64);
+    let mut fit_w = ((src_width as f64 * scale).round() as usize).clamp(1, width);
+    let mut fit_h = ((src_height as f64 * scale).round() as usize).clamp(1, height);
+    let mut x = (width - fit_w) / 2;
+    let mut y = (height - fit_h) / 2;
+
+    if format.is_yuv() {
+        // 就近取偶数，且不超过目标范围内的最大偶数
+        fit_w = ((fit_w + 1) & !1).clamp(2, width & !1);
+        fit_h = ((fit_h + 1) & !1).clamp(2, height & !1);
+        x = ((width - fit_w) / 2) & !1;
+        y = ((height - fit_h) / 2) & !1;
+    }
+    Ok((fit_w, fit_h, x, y))
+}
+
+/// 保持宽高比缩放到 `width`x`height`，空白区域填充黑边
+pub fn scale_to_fit(
+    frame: &Frame,
+    width: usize,
+    height: usize,
+    filter: ScaleFilter,
+) -> Result<Frame, NV12Error> {
+    let (fit_w, fit_h, x, y) =
+        fit_rect(frame.format(), frame.width(), frame.height(), width, height)?;
+    let scaled = scale_frame(frame, fit_w, fit_h, filter)?;
+
+    let mut output = Frame::black(frame.format(), width, height);
+    blit(&scaled, &mut output, x, y)?;
+    Ok(output)
+}
+
+/// 将 `src` 复制到 `dst` 的 (x, y) 处，超出部分被裁掉
+///
+/// 两帧格式必须一致；YUV 格式的坐标必须为偶数，否则色度平面会错位。
+pub fn blit(src: &Frame, dst: &mut Frame, x: usize, y: usize) -> Result<(), NV12Error> {
+    let format = dst.format();
+    if src.format() != format {
+        return Err(NV12Error::InvalidPlanes(format!(
+            "cannot blit {:?} onto {:?}",
+            src.format(),
+            format
+        )));
+    }
+    if format.is_yuv() && (!x.is_multiple_of(2) || !y.is_multiple_of(2)) {
+        return Err(NV12Error::InvalidPlanes(format!(
+            "blit offset ({}, {}) must be even for {:?}",
+            x, y, format
+        )));
+    }
+
+    let (dst_w, dst_h) = (dst.width(), dst.height());
+    for (i, (src_plane, dst_plane)) in src.planes().into_iter().zip(dst.planes_mut()).enumerate() {
+        let src_info = src.plane_info(i);
+        let dst_info = format.plane_info(i, dst_w, dst_h);
+        // 色度平面按采样比例换算偏移
+        let (px, py) = if i > 0 && format.is_yuv() {
+            (x / 2, y / 2)
+        } else {
+            (x, y)
+        };
+        if px >= dst_info.width || py >= dst_info.height {
+            continue;
+        }
+
+        let copy_w = src_info.width.min(dst_info.width - px) * src_info.channels;
+        let rows = src_info.height.min(dst_info.height - py);
+        for r in 0..rows {
+            let s = r * src_info.row_bytes();
+            let d = (py + r) * dst_info.row_bytes() + px * dst_info.channels;
+            dst_plane[d..d + copy_w].copy_from_slice(&src_plane[s..s + copy_w]);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scale_nv12_downscale() {
+        let mut frame = Frame::black(PixelFormat::Nv12, 8, 4);
+        frame.planes_mut()[0].fill(200);
+
+        for filter in [
+            ScaleFilter::Nearest,
+            ScaleFilter::Bilinear,
+            ScaleFilter::Bicubic,
+            ScaleFilter::Lanczos3,
+            ScaleFilter::Area,
+        ] {
+            let scaled = scale_frame(&frame, 4, 2, filter).unwrap();
+            assert_eq!(scaled.data().len(), PixelFormat::Nv12.frame_size(4, 2));
+            assert!(scaled.planes()[0].iter().all(|&v| v == 200), "{:?}", filter);
+            assert!(scaled.planes()[1].iter().all(|&v| v == 128), "{:?}", filter);
+        }
+    }
+
+    #[test]
+    fn test_area_averages_pixels() {
+        let data = vec![0, 0, 0, 255, 100, 100, 100, 255];
+        let frame = Frame::new(PixelFormat::Bgra, 2, 1, data).unwrap();
+        let scaled = scale_frame(&frame, 1, 1, ScaleFilter::Area).unwrap();
+        assert_eq!(scaled.data(), vec![50, 50, 50, 255]);
+    }
+
+    #[test]
+    fn test_scale_to_fit_letterbox() {
+        let mut frame = Frame::black(PixelFormat::Bgra, 4, 2);
+        frame.data_mut().fill(255);
+
+        let boxed = scale_to_fit(&frame, 4, 4, ScaleFilter::Bilinear).unwrap();
+        assert_eq!((boxed.width(), boxed.height()), (4, 4));
+        // 上下各一行黑边
+        assert_eq!(&boxed.data()[0..4], &[0, 0, 0, 255]);
+        assert_eq!(&boxed.data()[16..20], &[255, 255, 255, 255]);
+        assert_eq!(&boxed.data()[48..52], &[0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_scale_to_fit_zero_target() {
+        let frame = Frame::black(PixelFormat::Nv12, 4, 2);
+        assert!(scale_to_fit(&frame, 0, 4, ScaleFilter::Bilinear).is_err());
+        assert!(scale_to_fit(&frame, 4, 0, ScaleFilter::Bilinear).is_err());
+        assert!(fit_rect(PixelFormat::Nv12, 4, 2, 0, 0).is_err());
+        assert!(fit_rect(PixelFormat::Nv12, 4, 2, 1, 4).is_err());
+        assert_eq!(
+            fit_rect(PixelFormat::Nv12, 4, 2, 2, 2).unwrap(),
+            (2, 2, 0, 0)
+        );
+        assert_eq!(
+            fit_rect(PixelFormat::Nv12, 8, 2, 6, 6).unwrap(),
+            (6, 2, 0, 2)
+        );
+        assert_eq!(
+            fit_rect(PixelFormat::Bgra, 4, 2, 2, 2).unwrap(),
+            (2, 1, 0, 0)
+        );
+    }
+
+    #[test]
+    fn test_blit_rejects_mismatch() {
+        let src = Frame::black(PixelFormat::Nv12, 2, 2);
+        let mut dst = Frame::black(PixelFormat::Nv12, 4, 4);
+        assert!(blit(&src, &mut dst, 1, 0).is_err());
+        assert!(blit(&src, &mut dst, 0, 1).is_err());
+        assert!(blit(&src, &mut dst, 2, 2).is_ok());
+
+        let mut bgra = Frame::black(PixelFormat::Bgra, 4, 4);
+        assert!(blit(&src, &mut bgra, 0, 0).is_err());
+    }
+}
@@ -0,0 +1,386 @@
+//! 镜头切换与运动检测。
+//!
+//! 每帧计算亮度直方图和 8x8 单元平均亮度缩略图，与上一帧比较得到
+//! 直方图距离、平均绝对差 (SAD) 和分块运动图。可用于在幻灯片切换时
+//! 强制关键帧、挑选缩略图，以及在"静态文档"和"视频播放"之间调整帧率。
+
+use crate::convert::{to_luma, ColorMatrix};
+use crate::frame::Frame;
+use crate::overlay::Rect;
+
+/// 缩略图单元边长（亮度像素）
+const CELL: usize = 8;
+/// 亮度直方图桶数
+const HISTOGRAM_BINS: usize = 64;
+/// 活动度滑动平均的平滑系数
+const ACTIVITY_SMOOTHING: f32 = 0.2;
+
+/// 检测参数
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SceneDetectorOptions {
+    /// 直方图距离 (0.0-1.0) 达到该值视为切换
+    pub histogram_threshold: f32,
+    /// 平均绝对差 (0.0-1.0) 达到该值视为切换
+    pub sad_threshold: f32,
+    /// 两次切换之间至少间隔的帧数
+    pub min_interval: u64,
+    /// 运动图分块边长（亮度像素），向上取整到 8 的倍数
+    pub block_size: usize,
+    /// 分块平均差 (0.0-1.0) 超过该值视为运动
+    pub motion_threshold: f32,
+    /// 运动分块比例的滑动平均超过该值时进入 [`ContentMode::Moving`]，低于一半时回到静态
+    pub moving_fraction: f32,
+}
+
+impl Default for SceneDetectorOptions {
+    fn default() -> Self {
+        Self {
+            histogram_threshold: 0.35,
+            sad_threshold: 0.12,
+            min_interval: 5,
+            block_size: 64,
+            motion_threshold: 0.02,
+            moving_fraction: 0.02,
+        }
+    }
+}
+
+/// 画面内容类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ContentMode {
+    /// 文档、幻灯片等基本静止的画面
+    #[default]
+    Static,
+    /// 视频播放、滚动等持续变化的画面
+    Moving,
+}
+
+/// 分块运动图
+#[derive(Debug, Clone, PartialEq)]
+pub struct MotionMap {
+    pub columns: usize,
+    pub rows: usize,
+    /// 分块边长（亮度像素）
+    pub block_size: usize,
+    /// 按行存放的各分块平均亮度差 (0.0-1.0)
+    pub blocks: Vec<f32>,
+}
+
+impl MotionMap {
+    fn empty(columns: usize, rows: usize, block_size: usize) -> Self {
+        Self {
+            columns,
+            rows,
+            block_size,
+            blocks: vec![0.0; columns * rows],
+        }
+    }
+
+    pub fn get(&self, column: usize, row: usize) -> f32 {
+        self.blocks[row * self.columns + column]
+    }
+
+    /// 超过 `threshold` 的分块比例
+    pub fn moving_fraction(&self, threshold: f32) -> f32 {
+        if self.blocks.is_empty() {
+            return 0.0;
+        }
+        let moving = self.blocks.iter().filter(|&&b| b > threshold).count();
+        moving as f32 / self.blocks.len() as f32
+    }
+
+    /// 所有超过 `threshold` 的分块的外接矩形（亮度像素），没有运动时返回 `None`
+    pub fn bounding_box(&self, threshold: f32) -> Option<Rect> {
+        let mut bounds: Option<(usize, usize, usize, usize)> = None;
+        for row in 0..self.rows {
+            for column in 0..self.columns {
+                if self.get(column, row) <= threshold {
+                    continue;
+                }
+                bounds = Some(match bounds {
+                    None => (column, row, column, row),
+                    Some((x0, y0, x1, y1)) => {
+                        (x0.min(column), y0.min(row), x1.max(column), y1.max(row))
+                    }
+                });
+            }
+        }
+        bounds.map(|(x0, y0, x1, y1)| {
+            Rect::new(
+                x0 * self.block_size,
+                y0 * self.block_size,
+                (x1 - x0 + 1) * self.block_size,
+                (y1 - y0 + 1) * self.block_size,
+            )
+        })
+    }
+}
+
+/// 单帧分析结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameAnalysis {
+    /// 帧序号，从 0 开始
+    pub index: u64,
+    /// 与上一帧亮度直方图的距离 (0.0-1.0)
+    pub histogram_distance: f32,
+    /// 与上一帧的平均绝对差 (0.0-1.0)
+    pub sad: f32,
+    /// 是否为镜头切换；第一帧和尺寸变化后的帧总是切换，两项差异均记为 1.0
+    pub scene_change: bool,
+    pub motion: MotionMap,
+}
+
+/// 上一帧的特征
+struct Features {
+    width: usize,
+    height: usize,
+    histogram: Vec<f32>,
+    cells: Vec<f32>,
+    cell_columns: usize,
+}
+
+impl Features {
+    fn new(frame: &Frame) -> Self {
+        let (width, height) = (frame.width(), frame.height());
+        let luma = to_luma(frame, ColorMatrix::default());
+
+        let mut histogram = vec![0f32; HISTOGRAM_BINS];
+        for &v in &luma {
+            histogram[v as usize * HISTOGRAM_BINS / 256] += 1.0;
+        }
+        let total = luma.len().max(1) as f32;
+        histogram.iter_mut().for_each(|h| *h /= total);
+
+        let (cell_columns, cell_rows) = (width.div_ceil(CELL), height.div_ceil(CELL));
+        let mut cells = vec![0f32; cell_columns * cell_rows];
+        let mut counts = vec![0u32; cell_columns * cell_rows];
+        for y in 0..height {
+            let row = y / CELL * cell_columns;
+            for x in 0..width {
+                cells[row + x / CELL] += luma[y * width + x] as f32;
+                counts[row + x / CELL] += 1;
+            }
+        }
+        for (cell, count) in cells.iter_mut().zip(counts) {
+            *cell /= count as f32 * 255.0;
+        }
+
+        Self {
+            width,
+            height,
+            histogram,
+            cells,
+            cell_columns,
+        }
+    }
+
+    fn cell_rows(&self) -> usize {
+        self.cells.len() / self.cell_columns.max(1)
+    }
+}
+
+/// 逐帧检测镜头切换与运动
+pub struct SceneDetector {
+    options: SceneDetectorOptions,
+    previous: Option<Features>,
+    frames: u64,
+    last_change: Option<u64>,
+    activity: f32,
+    mode: ContentMode,
+}
+
+impl SceneDetector {
+    pub fn new(options: SceneDetectorOptions) -> Self {
+        Self {
+            options,
+            previous: None,
+            frames: 0,
+            last_change: None,
+            activity: 0.0,
+            mode: ContentMode::Static,
+        }
+    }
+
+    pub fn options(&self) -> &SceneDetectorOptions {
+        &self.options
+    }
+
+    /// 当前的内容类型，按运动分块比例的滑动平均判断并带有滞回
+    pub fn content_mode(&self) -> ContentMode {
+        self.mode
+    }
+
+    /// 清除历史，下一帧视为第一帧
+    pub fn reset(&mut self) {
+        self.previous = None;
+        self.frames = 0;
+        self.last_change = None;
+        self.activity = 0.0;
+        self.mode = ContentMode::Static;
+    }
+
+    /// 分析下一帧
+    pub fn analyze(&mut self, frame: &Frame) -> FrameAnalysis {
+        let index = self.frames;
+        self.frames += 1;
+
+        let features = Features::new(frame);
+        let cells_per_block = self.options.block_size.div_ceil(CELL).max(1);
+        let block_size = cells_per_block * CELL;
+        let columns = features.cell_columns.div_ceil(cells_per_block);
+        let rows = features.cell_rows().div_ceil(cells_per_block);
+
+        let previous = match self.previous.take() {
+            Some(previous)
+                if (previous.width, previous.height) == (frame.width(), frame.height()) =>
+            {
+                previous
+            }
+            _ => {
+                self.previous = Some(features);
+                self.last_change = Some(index);
+                return FrameAnalysis {
+                    index,
+                    histogram_distance: 1.0,
+                    sad: 1.0,
+                    scene_change: true,
+                    motion: MotionMap::empty(columns, rows, block_size),
+                };
+            }
+        };
+
+        let histogram_distance = previous
+            .histogram
+            .iter()
+            .zip(&features.histogram)
+            .map(|(a, b)| (a - b).abs())
+            .sum::<f32>()
+            / 2.0;
+
+        let mut motion = MotionMap::empty(columns, rows, block_size);
+        let mut counts = vec![0u32; columns * rows];
+        let mut sad = 0.0;
+        for (i, (a, b)) in previous.cells.iter().zip(&features.cells).enumerate() {
+            let diff = (a - b).abs();
+            sad += diff;
+            let (cx, cy) = (i % features.cell_columns, i / features.cell_columns);
+            let block = cy / cells_per_block * columns + cx / cells_per_block;
+            motion.blocks[block] += diff;
+            counts[block] += 1;
+        }
+        sad /= features.cells.len().max(1) as f32;
+        for (block, count) in motion.blocks.iter_mut().zip(counts) {
+            *block /= count.max(1) as f32;
+        }
+
+        let spaced = self
+            .last_change
+            .is_none_or(|last| index - last >= self.options.min_interval);
+        let scene_change = spaced
+            && (histogram_distance >= self.options.histogram_threshold
+                || sad >= self.options.sad_threshold);
+        if scene_change {
+            self.last_change = Some(index);
+        } else {
+            // 切换帧不计入活动度，避免一次翻页被当作视频播放
+            let fraction = motion.moving_fraction(self.options.motion_threshold);
+            self.activity += (fraction - self.activity) * ACTIVITY_SMOOTHING;
+            self.mode = match self.mode {
+                ContentMode::Static if self.activity > self.options.moving_fraction => {
+                    ContentMode::Moving
+                }
+                ContentMode::Moving if self.activity < self.options.moving_fraction / 2.0 => {
+                    ContentMode::Static
+                }
+                mode => mode,
+            };
+        }
+
+        self.previous = Some(features);
+        FrameAnalysis {
+            index,
+            histogram_distance,
+            sad,
+            scene_change,
+            motion,
+        }
+    }
+}
+
+impl Default for SceneDetector {
+    fn default() -> Self {
+        Self::new(SceneDetectorOptions::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::PixelFormat;
+
+    fn gray(value: u8) -> Frame {
+        let mut frame = Frame::black(PixelFormat::Nv12, 128, 64);
+        frame.planes_mut()[0].fill(value);
+        frame
+    }
+
+    #[test]
+    fn test_scene_change() {
+        let mut detector = SceneDetector::new(SceneDetectorOptions {
+            min_interval: 3,
+            ..Default::default()
+        });
+        assert!(detector.analyze(&gray(30)).scene_change);
+        let same = detector.analyze(&gray(30));
+        assert!(!same.scene_change);
+        assert_eq!(same.sad, 0.0);
+
+        // 距上次切换不足 3 帧，不报告
+        let early = detector.analyze(&gray(200));
+        assert!(early.histogram_distance > 0.9);
+        assert!(!early.scene_change);
+
+        detector.analyze(&gray(200));
+        let cut = detector.analyze(&gray(30));
+        assert!(cut.scene_change);
+        assert_eq!(cut.index, 4);
+
+        // 尺寸变化视为切换
+        assert!(
+            detector
+                .analyze(&Frame::black(PixelFormat::Nv12, 64, 64))
+                .scene_change
+        );
+    }
+
+    #[test]
+    fn test_motion_map_and_content_mode() {
+        let mut detector = SceneDetector::default();
+        let background = gray(100);
+        detector.analyze(&background);
+
+        let mut moving = background.clone();
+        for y in 0..16 {
+            moving.planes_mut()[0][y * 128 + 64..y * 128 + 96].fill(220);
+        }
+        let analysis = detector.analyze(&moving);
+        assert!(!analysis.scene_change);
+        assert_eq!((analysis.motion.columns, analysis.motion.rows), (2, 1));
+        assert_eq!(analysis.motion.get(0, 0), 0.0);
+        assert!(analysis.motion.get(1, 0) > 0.02);
+        assert_eq!(
+            analysis.motion.bounding_box(0.02),
+            Some(Rect::new(64, 0, 64, 64))
+        );
+
+        // 持续变化进入 Moving，静止后回到 Static
+        for i in 0..10 {
+            detector.analyze(if i % 2 == 0 { &background } else { &moving });
+        }
+        assert_eq!(detector.content_mode(), ContentMode::Moving);
+        for _ in 0..30 {
+            detector.analyze(&background);
+        }
+        assert_eq!(detector.content_mode(), ContentMode::Static);
+    }
+}
@@ -0,0 +1,289 @@
+use crate::frame::{Frame, PlaneInfo};
+use crate::nv12::NV12Error;
+
+/// 顺时针旋转角度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Rotation {
+    #[default]
+    None,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+}
+
+impl Rotation {
+    /// 旋转后宽高是否互换
+    pub fn swaps_dimensions(self) -> bool {
+        matches!(self, Rotation::Rotate90 | Rotation::Rotate270)
+    }
+}
+
+/// 裁剪区域
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Crop {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+/// 帧变换，按 裁剪 -> 翻转 -> 旋转 的顺序执行
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Transform {
+    pub crop: Option<Crop>,
+    pub flip_horizontal: bool,
+    pub flip_vertical: bool,
+    pub rotation: Rotation,
+}
+
+impl Transform {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_crop(mut self, crop: Crop) -> Self {
+        self.crop = Some(crop);
+        self
+    }
+
+    pub fn with_flip(mut self, horizontal: bool, vertical: bool) -> Self {
+        self.flip_horizontal = horizontal;
+        self.flip_vertical = vertical;
+        self
+    }
+
+    pub fn with_rotation(mut self, rotation: Rotation) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// 是否为恒等变换
+    pub fn is_identity(&self) -> bool {
+        self.crop.is_none()
+            && !self.flip_horizontal
+            && !self.flip_vertical
+            && self.rotation == Rotation::None
+    }
+
+    /// 对 `width`x`height` 的输入，变换后的输出尺寸
+    pub fn output_size(&self, width: usize, height: usize) -> (usize, usize) {
+        let (w, h) = self.crop.map_or((width, height), |c| (c.width, c.height));
+        if self.rotation.swaps_dimensions() {
+            (h, w)
+        } else {
+            (w, h)
+        }
+    }
+
+    /// 应用变换
+    pub fn apply(&self, frame: &Frame) -> Result<Frame, NV12Error> {
+        if self.is_identity() {
+            return Ok(frame.clone());
+        }
+
+        let cropped;
+        let source = match self.crop {
+            Some(region) => {
+                cropped = crop(frame, region)?;
+                &cropped
+            }
+            None => frame,
+        };
+
+        // 翻转与旋转合并为一次坐标映射
+        let (width, height) = (source.width(), source.height());
+        let (out_w, out_h) = if self.rotation.swaps_dimensions() {
+            (height, width)
+        } else {
+            (width, height)
+        };
+        let (flip_h, flip_v, rotation) = (self.flip_horizontal, self.flip_vertical, self.rotation);
+
+        Ok(remap(source, out_w, out_h, |dx, dy, w, h| {
+            // 输出坐标 -> 翻转后坐标
+            let (fx, fy) = match rotation {
+                Rotation::None => (dx, dy),
+                Rotation::Rotate90 => (dy, h - 1 - dx),
+                Rotation::Rotate180 => (w - 1 - dx, h - 1 - dy),
+                Rotation::Rotate270 => (w - 1 - dy, dx),
+            };
+            // 翻转后坐标 -> 源坐标
+            let sx = if flip_h { w - 1 - fx } else { fx };
+            let sy = if flip_v { h - 1 - fy } else { fy };
+            (sx, sy)
+        }))
+    }
+}
+
+/// 裁剪子区域
+///
+/// YUV 格式的裁剪起点会向下对齐到偶数，以保持色度采样对齐。
+pub fn crop(frame: &Frame, region: Crop) -> Result<Frame, NV12Error> {
+    let (x, y) = if frame.format().is_yuv() {
+        (region.x & !1, region.y & !1)
+    } else {
+        (region.x, region.y)
+    };
+
+    if region.width == 0
+        || region.height == 0
+        || x + region.width > frame.width()
+        || y + region.height > frame.height()
+    {
+        return Err(NV12Error::IndexOutOfBounds(format!(
+            "crop {}x{}+{}+{} outside {}x{} frame",
+            region.width,
+            region.height,
+            x,
+            y,
+            frame.width(),
+            frame.height()
+        )));
+    }
+
+    let mut output = Frame::black(frame.format(), region.width, region.height);
+    for (i, (src, dst)) in frame
+        .planes()
+        .into_iter()
+        .zip(output.planes_mut())
+        .enumerate()
+    {
+        let src_info = frame.plane_info(i);
+        let dst_info = frame.format().plane_info(i, region.width, region.height);
+        let (px, py) = plane_offset(frame, i, x, y);
+
+        for r in 0..dst_info.height {
+            let s = (py + r) * src_info.row_bytes() + px * src_info.channels;
+            let d = r * dst_info.row_bytes();
+            dst[d..d + dst_info.row_bytes()].copy_from_slice(&src[s..s + dst_info.row_bytes()]);
+        }
+    }
+    Ok(output)
+}
+
+/// 顺时针旋转
+pub fn rotate(frame: &Frame, rotation: Rotation) -> Frame {
+    Transform::new()
+        .with_rotation(rotation)
+        .apply(frame)
+        .expect("rotation without crop cannot fail")
+}
+
+/// 水平和/或垂直翻转
+pub fn flip(frame: &Frame, horizontal: bool, vertical: bool) -> Frame {
+    Transform::new()
+        .with_flip(horizontal, vertical)
+        .apply(frame)
+        .expect("flip without crop cannot fail")
+}
+
+/// 亮度坐标换算为第 `index` 个平面内的采样坐标
+fn plane_offset(frame: &Frame, index: usize, x: usize, y: usize) -> (usize, usize) {
+    if index > 0 && frame.format().is_yuv() {
+        (x / 2, y / 2)
+    } else {
+        (x, y)
+    }
+}
+
+/// 逐平面按坐标映射复制采样点，`map(dx, dy, 源平面宽, 源平面高)` 返回源坐标
+fn remap<F>(frame: &Frame, out_w: usize, out_h: usize, map: F) -> Frame
+where
+    F: Fn(usize, usize, usize, usize) -> (usize, usize),
+{
+    let mut output = Frame::black(frame.format(), out_w, out_h);
+    for (i, (src, dst)) in frame
+        .planes()
+        .into_iter()
+        .zip(output.planes_mut())
+        .enumerate()
+    {
+        let src_info: PlaneInfo = frame.plane_info(i);
+        let dst_info = frame.format().plane_info(i, out_w, out_h);
+        let channels = src_info.channels;
+
+        for dy in 0..dst_info.height {
+            for dx in 0..dst_info.width {
+                let (sx, sy) = map(dx, dy, src_info.width, src_info.height);
+                let s = sy * src_info.row_bytes() + sx * channels;
+                let d = dy * dst_info.row_bytes() + dx * channels;
+                dst[d..d + channels].copy_from_slice(&src[s..s + channels]);
+            }
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::PixelFormat;
+
+    fn luma_frame(width: usize, height: usize) -> Frame {
+        let mut frame = Frame::black(PixelFormat::I420, width, height);
+        for (i, v) in frame.planes_mut()[0].iter_mut().enumerate() {
+            *v = i as u8;
+        }
+        frame
+    }
+
+    #[test]
+    fn test_rotate_90_and_back() {
+        // 0 1 2
+        // 3 4 5
+        let frame = luma_frame(3, 2);
+        let rotated = rotate(&frame, Rotation::Rotate90);
+        assert_eq!((rotated.width(), rotated.height()), (2, 3));
+        assert_eq!(rotated.planes()[0], &[3, 0, 4, 1, 5, 2]);
+        assert_eq!(rotated.plane_info(1).width, 1);
+        assert_eq!(rotated.plane_info(1).height, 2);
+
+        let back = rotate(&rotated, Rotation::Rotate270);
+        assert_eq!(back, frame);
+        assert_eq!(
+            rotate(&rotate(&frame, Rotation::Rotate180), Rotation::Rotate180),
+            frame
+        );
+    }
+
+    #[test]
+    fn test_flip_bgra() {
+        let data = vec![1, 1, 1, 255, 2, 2, 2, 255];
+        let frame = Frame::new(PixelFormat::Bgra, 2, 1, data).unwrap();
+        let flipped = flip(&frame, true, false);
+        assert_eq!(flipped.data(), vec![2, 2, 2, 255, 1, 1, 1, 255]);
+    }
+
+    #[test]
+    fn test_crop_nv12_aligns_chroma() {
+        let mut frame = Frame::black(PixelFormat::Nv12, 4, 4);
+        {
+            let mut planes = frame.planes_mut();
+            for (i, v) in planes[0].iter_mut().enumerate() {
+                *v = i as u8;
+            }
+            for (i, v) in planes[1].iter_mut().enumerate() {
+                *v = 100 + i as u8;
+            }
+        }
+
+        let region = Crop {
+            x: 3,
+            y: 1,
+            width: 2,
+            height: 2,
+        };
+        let cropped = crop(&frame, region).unwrap();
+        // 起点对齐到 (2, 0)
+        assert_eq!(cropped.planes()[0], &[2, 3, 6, 7]);
+        assert_eq!(cropped.planes()[1], &[102, 103]);
+
+        let outside = Crop {
+            x: 2,
+            y: 2,
+            width: 4,
+            height: 2,
+        };
+        assert!(crop(&frame, outside).is_err());
+    }
+}
@@ -0,0 +1,242 @@
+//! 图片水印：把带透明通道的 logo 混合到采集帧上。
+//!
+//! 在 `ScreenCapture::capture_frame` 之后、`ObStream::write_frame` 之前调用 [`Watermark::apply`]。
+
+use crate::convert::{convert, ColorMatrix};
+use crate::frame::{Frame, PixelFormat};
+use crate::nv12::NV12Error;
+use crate::overlay::{Canvas, Placement};
+use crate::scale::{scale_frame, ScaleFilter};
+use image::{ImageFormat, ImageResult};
+use std::path::Path;
+
+/// logo 的显示尺寸
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LogoSize {
+    /// 原始尺寸
+    Original,
+    /// 相对原始尺寸的缩放倍数
+    Scale(f32),
+    /// 宽度占帧宽的比例，高度按宽高比计算
+    FrameWidth(f32),
+}
+
+/// 水印参数
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WatermarkOptions {
+    pub placement: Placement,
+    pub size: LogoSize,
+    /// 整体不透明度 (0.0-1.0)，与 logo 自身的透明通道相乘
+    pub opacity: f32,
+    pub filter: ScaleFilter,
+}
+
+impl Default for WatermarkOptions {
+    fn default() -> Self {
+        Self {
+            placement: Placement::default(),
+            size: LogoSize::Original,
+            opacity: 1.0,
+            filter: ScaleFilter::Bilinear,
+        }
+    }
+}
+
+/// 按帧尺寸缩放好的 logo
+struct PreparedLogo {
+    frame_size: (usize, usize),
+    x: usize,
+    y: usize,
+    image: Frame,
+}
+
+/// 图片水印
+///
+/// 缩放后的 logo 按帧尺寸缓存，帧尺寸不变时不会重复缩放。
+pub struct Watermark {
+    logo: Frame,
+    options: WatermarkOptions,
+    matrix: ColorMatrix,
+    prepared: Option<PreparedLogo>,
+}
+
+impl Watermark {
+    /// 由 logo 帧创建，非 RGBA 格式会先转换
+    pub fn new(logo: &Frame, options: WatermarkOptions) -> Self {
+        Self {
+            logo: convert(logo, PixelFormat::Rgba, ColorMatrix::default()),
+            options,
+            matrix: ColorMatrix::default(),
+            prepared: None,
+        }
+    }
+
+    /// 由 PNG 数据创建
+    pub fn from_png(bytes: &[u8], options: WatermarkOptions) -> ImageResult<Self> {
+        let image = image::load_from_memory_with_format(bytes, ImageFormat::Png)?;
+        Ok(Self::from_image(image, options))
+    }
+
+    /// 读取图片文件，格式由文件内容判断
+    pub fn open<P: AsRef<Path>>(path: P, options: WatermarkOptions) -> ImageResult<Self> {
+        Ok(Self::from_image(image::open(path)?, options))
+    }
+
+    fn from_image(image: image::DynamicImage, options: WatermarkOptions) -> Self {
+        let rgba = image.into_rgba8();
+        let (width, height) = (rgba.width() as usize, rgba.height() as usize);
+        let logo = Frame::new(PixelFormat::Rgba, width, height, rgba.into_raw())
+            .expect("RGBA buffer matches its dimensions");
+        Self::new(&logo, options)
+    }
+
+    /// logo 混合到 YUV 帧时使用的色彩矩阵，默认 BT.709
+    pub fn with_color_matrix(mut self, matrix: ColorMatrix) -> Self {
+        self.matrix = matrix;
+        self
+    }
+
+    pub fn options(&self) -> &WatermarkOptions {
+        &self.options
+    }
+
+    /// 更新参数，下一帧按新参数重新缩放
+    pub fn set_options(&mut self, options: WatermarkOptions) {
+        self.options = options;
+        self.prepared = None;
+    }
+
+    /// 把 logo 混合到帧上
+    pub fn apply(&mut self, frame: &mut Frame) -> Result<(), NV12Error> {
+        let frame_size = (frame.width(), frame.height());
+        if self.prepared.as_ref().map(|p| p.frame_size) != Some(frame_size) {
+            self.prepared = Some(self.prepare(frame_size)?);
+        }
+        let prepared = self.prepared.as_ref().unwrap();
+        let image = &prepared.image;
+
+        Canvas::new(frame).with_color_matrix(self.matrix).draw_rgba(
+            prepared.x,
+            prepared.y,
+            image.width(),
+            image.height(),
+            image.data(),
+            self.options.opacity,
+        );
+        Ok(())
+    }
+
+    /// 计算显示尺寸并缩放，尺寸超出帧时按宽高比缩小到帧内
+    fn prepare(&self, frame_size: (usize, usize)) -> Result<PreparedLogo, NV12Error> {
+        let (frame_w, frame_h) = frame_size;
+        let (logo_w, logo_h) = (self.logo.width() as f32, self.logo.height() as f32);
+        let mut scale = match self.options.size {
+            LogoSize::Original => 1.0,
+            LogoSize::Scale(scale) => scale,
+            LogoSize::FrameWidth(fraction) => frame_w as f32 * fraction / logo_w,
+        };
+        scale = scale
+            .min(frame_w as f32 / logo_w)
+            .min(frame_h as f32 / logo_h);
+        let width = ((logo_w * scale).round() as usize).max(1);
+        let height = ((logo_h * scale).round() as usize).max(1);
+
+        let image = if (width, height) == (self.logo.width(), self.logo.height()) {
+            self.logo.clone()
+        } else {
+            // 预乘透明度后缩放，避免透明像素的颜色渗入边缘
+            let scaled = scale_frame(&premultiply(&self.logo), width, height, self.options.filter)?;
+            unpremultiply(scaled)
+        };
+
+        let (x, y) = self
+            .options
+            .placement
+            .resolve(width, height, frame_w, frame_h);
+        Ok(PreparedLogo {
+            frame_size,
+            x,
+            y,
+            image,
+        })
+    }
+}
+
+fn premultiply(rgba: &Frame) -> Frame {
+    let mut output = rgba.clone();
+    for p in output.data_mut().chunks_exact_mut(4) {
+        let alpha = p[3] as u32;
+        for c in &mut p[..3] {
+            *c = ((*c as u32 * alpha + 127) / 255) as u8;
+        }
+    }
+    output
+}
+
+fn unpremultiply(mut rgba: Frame) -> Frame {
+    for p in rgba.data_mut().chunks_exact_mut(4) {
+        let alpha = p[3] as u32;
+        if alpha == 0 {
+            continue;
+        }
+        for c in &mut p[..3] {
+            *c = ((*c as u32 * 255 + alpha / 2) / alpha).min(255) as u8;
+        }
+    }
+    rgba
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::overlay::Anchor;
+
+    fn logo() -> Frame {
+        // 左侧不透明红色，右侧全透明
+        let data = [[255, 0, 0, 255], [0, 255, 0, 0]].concat().repeat(2);
+        Frame::new(PixelFormat::Rgba, 2, 2, data).unwrap()
+    }
+
+    #[test]
+    fn test_watermark_bgra() {
+        let options = WatermarkOptions {
+            placement: Placement::At { x: 1, y: 1 },
+            opacity: 0.5,
+            ..Default::default()
+        };
+        let mut watermark = Watermark::new(&logo(), options);
+        let mut frame = Frame::black(PixelFormat::Bgra, 4, 4);
+        watermark.apply(&mut frame).unwrap();
+
+        let pixel = |x: usize, y: usize| &frame.data()[(y * 4 + x) * 4..(y * 4 + x) * 4 + 4];
+        assert_eq!(pixel(1, 1), &[0, 0, 128, 255]);
+        assert_eq!(pixel(2, 1), &[0, 0, 0, 255]);
+        assert_eq!(pixel(0, 0), &[0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_watermark_nv12_scaled() {
+        let options = WatermarkOptions {
+            placement: Placement::Anchored {
+                anchor: Anchor::BottomRight,
+                margin: 0,
+            },
+            size: LogoSize::FrameWidth(0.5),
+            ..Default::default()
+        };
+        let mut watermark = Watermark::new(&logo(), options);
+        let mut frame = Frame::black(PixelFormat::Nv12, 8, 8);
+        watermark.apply(&mut frame).unwrap();
+        let prepared = watermark.prepared.as_ref().unwrap();
+        assert_eq!((prepared.image.width(), prepared.image.height()), (4, 4));
+        assert_eq!((prepared.x, prepared.y), (4, 4));
+
+        let planes = frame.planes();
+        // 左上角未被覆盖，logo 左半部分的亮度被抬高
+        assert_eq!(planes[0][0], 16);
+        assert!(planes[0][7 * 8 + 4] > 16);
+        assert_eq!(planes[0][7 * 8 + 7], 16);
+        // 红色抬高 Cr
+        assert!(planes[1][3 * 8 + 2 * 2 + 1] > 128);
+    }
+}
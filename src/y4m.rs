@@ -0,0 +1,430 @@
+use crate::convert::{convert, ColorMatrix};
+use crate::frame::{Frame, PixelFormat};
+use crate::nv12::NV12Error;
+use std::io::{self, BufRead, Write};
+
+const STREAM_MAGIC: &str = "YUV4MPEG2";
+const FRAME_MAGIC: &str = "FRAME";
+
+/// 采样值范围
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorRange {
+    /// 限制范围 (16-235)
+    #[default]
+    Limited,
+    /// 全范围 (0-255)
+    Full,
+}
+
+/// 4:2:0 色度采样位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChromaSiting {
+    /// 色度位于两个亮度采样的中心 (C420jpeg)
+    Center,
+    /// 色度与左侧亮度对齐，H.264/NV12 的默认位置 (C420mpeg2)
+    #[default]
+    Left,
+}
+
+/// YUV4MPEG2 流头
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Y4mHeader {
+    pub width: usize,
+    pub height: usize,
+    /// 帧率分子
+    pub fps_num: u32,
+    /// 帧率分母
+    pub fps_den: u32,
+    pub range: ColorRange,
+    pub siting: ChromaSiting,
+}
+
+impl Y4mHeader {
+    pub fn new(width: usize, height: usize, fps_num: u32, fps_den: u32) -> Self {
+        Self {
+            width,
+            height,
+            fps_num,
+            fps_den,
+            range: ColorRange::default(),
+            siting: ChromaSiting::default(),
+        }
+    }
+
+    /// 每帧 I420 数据字节数
+    pub fn frame_size(&self) -> usize {
+        PixelFormat::I420.frame_size(self.width, self.height)
+    }
+
+    fn to_line(self) -> String {
+        let chroma = match self.siting {
+            ChromaSiting::Center => "420jpeg",
+            ChromaSiting::Left => "420mpeg2",
+        };
+        let range = match self.range {
+            ColorRange::Limited => "LIMITED",
+            ColorRange::Full => "FULL",
+        };
+        format!(
+            "{} W{} H{} F{}:{} Ip A1:1 C{} XCOLORRANGE={}\n",
+            STREAM_MAGIC, self.width, self.height, self.fps_num, self.fps_den, chroma, range
+        )
+    }
+
+    fn parse(line: &str) -> Result<Self, Y4mError> {
+        let mut tokens = line.split_ascii_whitespace();
+        if tokens.next() != Some(STREAM_MAGIC) {
+            return Err(Y4mError::Header("missing YUV4MPEG2 signature".to_string()));
+        }
+
+        let mut header = Y4mHeader::new(0, 0, 25, 1);
+        for token in tokens {
+            let mut chars = token.chars();
+            let tag = chars.next();
+            let value = chars.as_str();
+            match tag {
+                Some('W') => header.width = parse_number(value, "width")?,
+                Some('H') => header.height = parse_number(value, "height")?,
+                Some('F') => {
+                    let (num, den) = value
+                        .split_once(':')
+                        .ok_or_else(|| Y4mError::Header(format!("invalid frame rate {}", value)))?;
+                    header.fps_num = parse_number(num, "frame rate")?;
+                    header.fps_den = parse_number(den, "frame rate")?;
+                }
+                Some('I') if value != "p" && value != "?" => {
+                    return Err(Y4mError::Header(format!(
+                        "interlacing {} is not supported",
+                        value
+                    )))
+                }
+                Some('C') => {
+                    header.siting = match value {
+                        "420jpeg" | "420" => ChromaSiting::Center,
+                        "420mpeg2" | "420paldv" => ChromaSiting::Left,
+                        _ => {
+                            return Err(Y4mError::Header(format!(
+                                "colorspace C{} is not supported",
+                                value
+                            )))
+                        }
+                    }
+                }
+                Some('X') => match value {
+                    "COLORRANGE=FULL" => header.range = ColorRange::Full,
+                    "COLORRANGE=LIMITED" => header.range = ColorRange::Limited,
+                    _ => {}
+                },
+                // 像素宽高比、注释等忽略
+                _ => {}
+            }
+        }
+
+        if header.width == 0 || header.height == 0 {
+            return Err(Y4mError::Header("missing frame size".to_string()));
+        }
+        if header.fps_num == 0 || header.fps_den == 0 {
+            return Err(Y4mError::Header("invalid frame rate".to_string()));
+        }
+        Ok(header)
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str, name: &str) -> Result<T, Y4mError> {
+    value
+        .parse()
+        .map_err(|_| Y4mError::Header(format!("invalid {} {}", name, value)))
+}
+
+/// 写入 YUV4MPEG2 流
+pub struct Y4mWriter<W: Write> {
+    inner: W,
+    header: Y4mHeader,
+    matrix: ColorMatrix,
+    frames_written: u64,
+}
+
+impl<W: Write> Y4mWriter<W> {
+    /// 创建写入器并立即写出流头
+    pub fn new(mut inner: W, header: Y4mHeader) -> Result<Self, Y4mError> {
+        inner.write_all(header.to_line().as_bytes())?;
+        Ok(Self {
+            inner,
+            header,
+            matrix: ColorMatrix::default(),
+            frames_written: 0,
+        })
+    }
+
+    /// RGB 帧转换为 YUV 时使用的色彩矩阵，默认 BT.709
+    pub fn with_color_matrix(mut self, matrix: ColorMatrix) -> Self {
+        self.matrix = matrix;
+        self
+    }
+
+    pub fn header(&self) -> &Y4mHeader {
+        &self.header
+    }
+
+    pub fn frames_written(&self) -> u64 {
+        self.frames_written
+    }
+
+    /// 写入一帧，NV12 和 RGB 帧会先转换为 I420
+    ///
+    /// YUV 帧的采样范围视为与流头一致；RGB 帧按流头的 `range` 量化。
+    pub fn write_frame(&mut self, frame: &Frame) -> Result<(), Y4mError> {
+        if frame.width() != self.header.width || frame.height() != self.header.height {
+            return Err(Y4mError::Frame(NV12Error::InvalidPlanes(format!(
+                "frame is {}x{}, stream is {}x{}",
+                frame.width(),
+                frame.height(),
+                self.header.width,
+                self.header.height
+            ))));
+        }
+
+        let i420;
+        let data = if frame.format() == PixelFormat::I420 {
+            &frame.data()
+        } else {
+            let mut yuv = convert(frame, PixelFormat::I420, self.matrix);
+            if !frame.format().is_yuv() {
+                remap_range(&mut yuv, ColorRange::Limited, self.header.range);
+            }
+            i420 = yuv;
+            &i420.data()
+        };
+
+        self.inner.write_all(FRAME_MAGIC.as_bytes())?;
+        self.inner.write_all(b"\n")?;
+        self.inner.write_all(data)?;
+        self.frames_written += 1;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<(), Y4mError> {
+        self.inner.flush()?;
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+/// 读取 YUV4MPEG2 流
+pub struct Y4mReader<R: BufRead> {
+    inner: R,
+    header: Y4mHeader,
+}
+
+impl<R: BufRead> Y4mReader<R> {
+    /// 读取并解析流头
+    pub fn new(mut inner: R) -> Result<Self, Y4mError> {
+        let line =
+            read_line(&mut inner)?.ok_or_else(|| Y4mError::Header("empty stream".to_string()))?;
+        let header = Y4mHeader::parse(&line)?;
+        Ok(Self { inner, header })
+    }
+
+    pub fn header(&self) -> &Y4mHeader {
+        &self.header
+    }
+
+    /// 读取下一帧（I420），流结束时返回 `None`
+    pub fn read_frame(&mut self) -> Result<Option<Frame>, Y4mError> {
+        let line = match read_line(&mut self.inner)? {
+            Some(line) => line,
+            None => return Ok(None),
+        };
+        if line.split_ascii_whitespace().next() != Some(FRAME_MAGIC) {
+            return Err(Y4mError::Header(format!("expected FRAME, got {:?}", line)));
+        }
+
+        let mut data = vec![0u8; self.header.frame_size()];
+        self.inner.read_exact(&mut data)?;
+        let frame = Frame::new(
+            PixelFormat::I420,
+            self.header.width,
+            self.header.height,
+            data,
+        )?;
+        Ok(Some(frame))
+    }
+
+    /// 读取下一帧并转换为指定格式，转换为 RGB 时按流头的 `range` 解释采样
+    pub fn read_frame_as(&mut self, format: PixelFormat) -> Result<Option<Frame>, Y4mError> {
+        let range = self.header.range;
+        Ok(self.read_frame()?.map(|mut frame| {
+            if !format.is_yuv() {
+                remap_range(&mut frame, range, ColorRange::Limited);
+            }
+            convert(&frame, format, ColorMatrix::default())
+        }))
+    }
+}
+
+impl<R: BufRead> Iterator for Y4mReader<R> {
+    type Item = Result<Frame, Y4mError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_frame().transpose()
+    }
+}
+
+/// 在两种采样范围之间重映射 YUV 帧，色彩矩阵只处理限制范围
+fn remap_range(frame: &mut Frame, from: ColorRange, to: ColorRange) {
+    if from == to {
+        return;
+    }
+    for (i, plane) in frame.planes_mut().into_iter().enumerate() {
+        // (限制范围起点, 全范围起点, 限制范围跨度)
+        let (limited, full, span) = if i == 0 {
+            (16.0, 0.0, 219.0)
+        } else {
+            (128.0, 128.0, 224.0)
+        };
+        for v in plane.iter_mut() {
+            let value = match to {
+                ColorRange::Full => (*v as f32 - limited) * 255.0 / span + full,
+                ColorRange::Limited => (*v as f32 - full) * span / 255.0 + limited,
+            };
+            *v = value.round().clamp(0.0, 255.0) as u8;
+        }
+    }
+}
+
+/// 读取以 '\n' 结尾的一行，EOF 时返回 `None`
+fn read_line<R: BufRead>(reader: &mut R) -> Result<Option<String>, Y4mError> {
+    let mut line = Vec::new();
+    if reader.read_until(b'\n', &mut line)? == 0 {
+        return Ok(None);
+    }
+    if line.last() != Some(&b'\n') {
+        return Err(Y4mError::Header("truncated header line".to_string()));
+    }
+    line.pop();
+    String::from_utf8(line)
+        .map(Some)
+        .map_err(|_| Y4mError::Header("header is not valid UTF-8".to_string()))
+}
+
+/// Y4M 读写错误
+#[derive(Debug)]
+pub enum Y4mError {
+    Io(io::Error),
+    Header(String),
+    Frame(NV12Error),
+}
+
+impl std::fmt::Display for Y4mError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Y4mError::Io(err) => write!(f, "I/O error: {}", err),
+            Y4mError::Header(msg) => write!(f, "Invalid header: {}", msg),
+            Y4mError::Frame(err) => write!(f, "Invalid frame: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for Y4mError {}
+
+impl From<io::Error> for Y4mError {
+    fn from(err: io::Error) -> Self {
+        Y4mError::Io(err)
+    }
+}
+
+impl From<NV12Error> for Y4mError {
+    fn from(err: NV12Error) -> Self {
+        Y4mError::Frame(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nv12_pattern(width: usize, height: usize) -> Frame {
+        let mut frame = Frame::black(PixelFormat::Nv12, width, height);
+        let mut planes = frame.planes_mut();
+        for (i, v) in planes[0].iter_mut().enumerate() {
+            *v = 16 + i as u8;
+        }
+        for (i, v) in planes[1].iter_mut().enumerate() {
+            *v = 100 + i as u8;
+        }
+        frame
+    }
+
+    #[test]
+    fn test_write_read_roundtrip() {
+        let frame = nv12_pattern(4, 2);
+        let mut header = Y4mHeader::new(4, 2, 30000, 1001);
+        header.range = ColorRange::Full;
+
+        let mut writer = Y4mWriter::new(Vec::new(), header).unwrap();
+        writer.write_frame(&frame).unwrap();
+        writer.write_frame(&frame).unwrap();
+        assert_eq!(writer.frames_written(), 2);
+        let bytes = writer.into_inner();
+
+        let expected_header = "YUV4MPEG2 W4 H2 F30000:1001 Ip A1:1 C420mpeg2 XCOLORRANGE=FULL\n";
+        assert!(bytes.starts_with(expected_header.as_bytes()));
+        // I420: U 平面 [100, 102]，V 平面 [101, 103]
+        let payload = &bytes[expected_header.len() + 6..];
+        assert_eq!(&payload[8..12], &[100, 102, 101, 103]);
+
+        let mut reader = Y4mReader::new(&bytes[..]).unwrap();
+        assert_eq!(*reader.header(), header);
+        let read = reader.read_frame_as(PixelFormat::Nv12).unwrap().unwrap();
+        assert_eq!(read, frame);
+        assert!(reader.read_frame().unwrap().is_some());
+        assert!(reader.read_frame().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_full_range_samples() {
+        // 左白右黑
+        let mut rgba = Frame::black(PixelFormat::Rgba, 4, 2);
+        for (i, px) in rgba.data_mut().chunks_exact_mut(4).enumerate() {
+            let v = if i % 4 < 2 { 255 } else { 0 };
+            px.copy_from_slice(&[v, v, v, 255]);
+        }
+
+        for (range, white, black) in [(ColorRange::Full, 255, 0), (ColorRange::Limited, 235, 16)] {
+            let mut header = Y4mHeader::new(4, 2, 25, 1);
+            header.range = range;
+            let mut writer = Y4mWriter::new(Vec::new(), header).unwrap();
+            writer.write_frame(&rgba).unwrap();
+            let bytes = writer.into_inner();
+
+            let mut reader = Y4mReader::new(&bytes[..]).unwrap();
+            let i420 = reader.read_frame().unwrap().unwrap();
+            assert_eq!(&i420.planes()[0][..4], &[white, white, black, black]);
+            assert_eq!(i420.planes()[1], &[128, 128]);
+
+            let mut reader = Y4mReader::new(&bytes[..]).unwrap();
+            let read = reader.read_frame_as(PixelFormat::Rgba).unwrap().unwrap();
+            assert_eq!(read, rgba);
+        }
+    }
+
+    #[test]
+    fn test_invalid_header() {
+        assert!(matches!(
+            Y4mReader::new(&b"YUV4MPEG2 W4 H2 F25:1 C444\n"[..]),
+            Err(Y4mError::Header(_))
+        ));
+        assert!(matches!(
+            Y4mReader::new(&b"RIFF"[..]),
+            Err(Y4mError::Header(_))
+        ));
+
+        let header = Y4mHeader::new(4, 2, 25, 1);
+        let mut writer = Y4mWriter::new(Vec::new(), header).unwrap();
+        let wrong_size = Frame::black(PixelFormat::I420, 2, 2);
+        assert!(writer.write_frame(&wrong_size).is_err());
+    }
+}